@@ -0,0 +1,78 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::commitment::poseidon_commit;
+use halo2_graph::gadget::decimal::Decimal;
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions,
+};
+use halo2_graph::gadget::stats::{self, Normalization};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Vec<Decimal>>, // Private data, one vector per variable, numbers or decimal strings
+    #[serde(default)]
+    pub normalization: Normalization, // "population" or "sample"
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn descriptive_stats_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_decimal_matrix(ctx, &input.x);
+
+    // 2. means, covariance and correlation matrices
+    let means: Vec<AssignedValue<F>> =
+        x_values.iter().map(|column| stats::mean(&fixed_point_chip, ctx, column)).collect();
+    let covariance =
+        stats::covariance_matrix(&fixed_point_chip, ctx, &x_values, input.normalization);
+    let correlation = stats::correlation_matrix(&fixed_point_chip, ctx, &covariance);
+
+    for (i, m) in means.iter().enumerate() {
+        println!("mean{}: {:?}", i, fixed_point_chip.dequantization(*m.value()));
+    }
+    for (label, matrix) in [("cov", &covariance), ("corr", &correlation)] {
+        for (i, row) in matrix.iter().enumerate() {
+            let row: Vec<f64> =
+                row.iter().map(|c| fixed_point_chip.dequantization(*c.value())).collect();
+            println!("{}{}: {:?}", label, i, row);
+        }
+    }
+
+    // 3. commit to the dataset
+    let values: Vec<AssignedValue<F>> = x_values.iter().flatten().copied().collect();
+    let dataset_hash = poseidon_commit(ctx, fixed_point_chip.gate(), &values);
+
+    // public instances: [means..., covariance matrix row by row..., correlation matrix row by
+    // row..., dataset commitment]
+    make_public.extend(means);
+    make_public.extend(covariance.into_iter().flatten());
+    make_public.extend(correlation.into_iter().flatten());
+    make_public.push(dataset_hash);
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(descriptive_stats_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
@@ -1,7 +1,9 @@
 use clap::Parser;
 use halo2_base::utils::{ScalarField, BigPrimeField};
 use halo2_base::AssignedValue;
-use halo2_graph::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions, Rounding,
+};
 use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
 use serde::{Serialize, Deserialize};
 #[allow(unused_imports)]
@@ -12,6 +14,12 @@ use halo2_graph::scaffold::run;
 pub struct CircuitInput {
     pub x: f64,
     pub y: f64,
+    #[serde(default)]
+    pub checked: bool, // Reject results outside of the valid range instead of wrapping
+    #[serde(default)]
+    pub rounding: Option<Rounding>, // "floor", "half_even" or "toward_zero", chip defaults if unset
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
 }
 
 fn fixed_point_mul<F: ScalarField>(
@@ -19,9 +27,14 @@ fn fixed_point_mul<F: ScalarField>(
     input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
 ) where  F: BigPrimeField {
-    const PRECISION: u32 = 63;
     println!("build_lookup_bit: {:?}", builder.lookup_bits());
-    let fixed_point_chip = FixedPointChip::<F, PRECISION>::default(builder);
+    let mut fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    if let Some(rounding) = input.rounding {
+        fixed_point_chip = fixed_point_chip.with_rounding(rounding);
+    }
+    if input.checked {
+        fixed_point_chip = fixed_point_chip.with_overflow_checks();
+    }
     let ctx = builder.main(0);
 
     let x_decimal: f64 = input.x;
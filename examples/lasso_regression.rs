@@ -0,0 +1,82 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::fixed_point::{default_precision_bits, FixedPointChip};
+use halo2_graph::gadget::regression::{
+    commit_dataset, default_max_abs, lasso, load_coefficients, ols,
+};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Vec<f64>>, // Matrix of independent variables, one vector per feature
+    pub y: Vec<f64>,      // Dependent variable
+    pub coefficients: Vec<f64>, // Coefficients including intercept
+    pub lambda: f64,      // L1 penalty, the intercept is not penalized
+    pub tolerance: f64,   // Allowed error per KKT condition
+    #[serde(default = "default_max_abs")]
+    pub max_abs: f64, // Bound on every |x| and |y|, constrained in-circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn lasso_regression_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.x);
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.y);
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let lambda = ctx.load_witness(fixed_point_chip.quantization(input.lambda));
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+
+    // 2. constrain the KKT conditions of the Lasso problem with X = [1, x1, ..., xk]
+    let design_matrix = ols::with_intercept(&fixed_point_chip, ctx, &x_values);
+    lasso::verify_kkt(
+        &fixed_point_chip,
+        ctx,
+        &design_matrix,
+        &y_values,
+        &coefficients,
+        lambda,
+        tolerance,
+        true,
+        &ols::DataBounds::uniform(input.x.len(), input.max_abs).with_intercept(),
+    );
+
+    for (i, b) in coefficients.iter().enumerate() {
+        println!("b{}: {:?}", i, fixed_point_chip.dequantization(*b.value()));
+    }
+
+    // 3. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &x_values, &y_values);
+
+    // public instances: [b0, ..., bk, lambda, tolerance, dataset commitment]
+    make_public.extend(coefficients);
+    make_public.push(lambda);
+    make_public.push(tolerance);
+    make_public.push(dataset_hash);
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(lasso_regression_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
@@ -0,0 +1,131 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::fixed_point::{default_precision_bits, FixedPointChip};
+use halo2_graph::gadget::regression::{
+    commit_dataset, default_max_abs, fit, load_coefficients, ols, significance,
+};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Vec<f64>>, // Matrix of independent variables, one vector per feature
+    pub y: Vec<f64>,      // Dependent variable
+    pub coefficients: Vec<f64>, // Coefficients including intercept
+    pub tolerance: f64,   // Allowed error per normal equation
+    pub inverse_tolerance: f64, // Allowed error per entry of (X^T X) M = I
+    pub t_threshold: Option<f64>, // If set, |t| >= t_threshold is proven for `significant`
+    #[serde(default)]
+    pub significant: Vec<usize>, // Indices of the coefficients claimed to be significant (0 to k)
+    #[serde(default = "default_max_abs")]
+    pub max_abs: f64, // Bound on every |x| and |y|, constrained in-circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn linregress_significance_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.x);
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.y);
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+    let inverse_tolerance =
+        ctx.load_witness(fixed_point_chip.quantization(input.inverse_tolerance));
+
+    // 2. constrain the coefficients to be the least squares solution
+    let design_matrix = ols::with_intercept(&fixed_point_chip, ctx, &x_values);
+    let bounds = ols::DataBounds::uniform(input.x.len(), input.max_abs).with_intercept();
+    ols::verify_residual_orthogonality(
+        &fixed_point_chip,
+        ctx,
+        &design_matrix,
+        &y_values,
+        &coefficients,
+        tolerance,
+        &bounds,
+    );
+
+    // 3. standard errors and t-statistics
+    let rss =
+        fit::residual_sum_of_squares(&fixed_point_chip, ctx, &design_matrix, &y_values, &coefficients);
+    let statistics = significance::coefficient_statistics(
+        &fixed_point_chip,
+        ctx,
+        &design_matrix,
+        &bounds.x,
+        &coefficients,
+        rss,
+        inverse_tolerance,
+    );
+    println!("sigma^2: {:?}", fixed_point_chip.dequantization(*statistics.sigma_squared.value()));
+    for (i, (se, t)) in
+        statistics.standard_errors.iter().zip(statistics.t_statistics.iter()).enumerate()
+    {
+        println!(
+            "b{}: {:?}, se: {:?}, t: {:?}",
+            i,
+            fixed_point_chip.dequantization(*coefficients[i].value()),
+            fixed_point_chip.dequantization(*se.value()),
+            fixed_point_chip.dequantization(*t.value()),
+        );
+    }
+
+    // 4. prove the significance claims for the selected coefficients
+    let mask = significance::selection_mask(statistics.t_statistics.len(), &input.significant)
+        .unwrap_or_else(|e| panic!("invalid `significant`: {e}"));
+    let t_threshold = input.t_threshold.map(|threshold| {
+        let threshold = ctx.load_witness(fixed_point_chip.quantization(threshold));
+        let selected: Vec<AssignedValue<F>> =
+            mask.iter().map(|s| ctx.load_witness(F::from(*s as u64))).collect();
+        significance::assert_selected_significant(
+            &fixed_point_chip,
+            ctx,
+            &statistics.t_statistics,
+            &selected,
+            threshold,
+        );
+        (threshold, selected)
+    });
+
+    // 5. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &x_values, &y_values);
+
+    // public instances: [b0, ..., bk, tolerance, inverse tolerance, dataset commitment,
+    //                    se0, ..., sek, t0, ..., tk, (t_threshold, s0, ..., sk)]
+    // where s_i = 1 if coefficient i is claimed to be significant
+    make_public.extend(coefficients);
+    make_public.push(tolerance);
+    make_public.push(inverse_tolerance);
+    make_public.push(dataset_hash);
+    make_public.extend(statistics.standard_errors);
+    make_public.extend(statistics.t_statistics);
+    if let Some((threshold, selected)) = t_threshold {
+        make_public.push(threshold);
+        make_public.extend(selected);
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(linregress_significance_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
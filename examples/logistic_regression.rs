@@ -0,0 +1,97 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_base::gates::GateInstructions;
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions,
+};
+use halo2_graph::gadget::regression::{commit_dataset, load_coefficients, logistic, ols};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+use halo2_base::QuantumCell::Constant;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Vec<f64>>, // Matrix of independent variables, one vector per feature
+    pub y: Vec<f64>,      // Labels, 0 or 1
+    pub coefficients: Vec<f64>, // Coefficients including intercept
+    pub tolerance: f64,   // Allowed error per stationarity condition
+    pub queries: Vec<Vec<f64>>, // Rows to predict, one vector per feature
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn logistic_regression_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.x);
+    assert!(input.y.iter().all(|y| *y == 0.0 || *y == 1.0), "labels must be 0 or 1");
+    let scale = Constant(fixed_point_chip.quantization_scale);
+    let y_values: Vec<AssignedValue<F>> = input
+        .y
+        .iter()
+        .map(|y| {
+            let label = ctx.load_witness(F::from(*y as u64));
+            fixed_point_chip.gate().assert_bit(ctx, label);
+            fixed_point_chip.gate().mul(ctx, label, scale)
+        })
+        .collect();
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+    let queries: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.queries);
+
+    // 2. constrain the coefficients to be a stationary point of the log-likelihood
+    let design_matrix = ols::with_intercept(&fixed_point_chip, ctx, &x_values);
+    logistic::verify_stationarity(
+        &fixed_point_chip,
+        ctx,
+        &design_matrix,
+        &y_values,
+        &coefficients,
+        tolerance,
+    );
+
+    // 3. predict the probabilities of the query rows
+    let query_matrix = ols::with_intercept(&fixed_point_chip, ctx, &queries);
+    let predictions = logistic::predict(&fixed_point_chip, ctx, &query_matrix, &coefficients);
+
+    for (i, b) in coefficients.iter().enumerate() {
+        println!("b{}: {:?}", i, fixed_point_chip.dequantization(*b.value()));
+    }
+    for (i, p) in predictions.iter().enumerate() {
+        println!("p{}: {:?}", i, fixed_point_chip.dequantization(*p.value()));
+    }
+
+    // 4. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &x_values, &y_values);
+
+    // public instances: [b0, ..., bk, tolerance, dataset commitment, queries..., predictions...]
+    make_public.extend(coefficients);
+    make_public.push(tolerance);
+    make_public.push(dataset_hash);
+    make_public.extend(queries.into_iter().flatten());
+    make_public.extend(predictions);
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(logistic_regression_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
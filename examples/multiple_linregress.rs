@@ -1,146 +1,98 @@
 use std::time::Instant;
 use clap::Parser;
 use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
-use halo2_graph::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_graph::gadget::fixed_point::{default_precision_bits, FixedPointChip, Rounding};
+use halo2_graph::gadget::regression::{
+    commit_dataset, default_max_abs, fit, load_coefficients, ols,
+};
+use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
 use halo2_base::utils::BigPrimeField;
 use halo2_base::AssignedValue;
-use halo2_base::QuantumCell;
 
 #[allow(unused_imports)]
 use halo2_graph::scaffold::cmd::Cli;
-use halo2_graph::scaffold::run;
+use halo2_graph::scaffold::run_with_report;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
-    pub x: Vec<Vec<f64>>, // Matrix of independent variables
+    pub x: Vec<Vec<f64>>, // Matrix of independent variables, one vector per feature
     pub y: Vec<f64>,      // Dependent variable
     pub coefficients: Vec<f64>, // Coefficients including intercept
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64, // Allowed error per normal equation
+    #[serde(default)]
+    pub mode: ols::Verification, // "normal_equations" or "residual_orthogonality"
+    #[serde(default)]
+    pub rounding: Option<Rounding>, // "floor", "half_even" or "toward_zero", chip defaults if unset
+    #[serde(default = "default_max_abs")]
+    pub max_abs: f64, // Bound on every |x| and |y|, constrained in-circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn default_tolerance() -> f64 {
+    0.1
 }
 
 fn multiple_linear_regression_circuit<F: BigPrimeField>(
     builder: &mut BaseCircuitBuilder<F>,
     input: CircuitInput,
-    _make_public: &mut Vec<AssignedValue<F>>,
+    make_public: &mut Vec<AssignedValue<F>>,
 ) where F: BigPrimeField {
-    // steps based on https://www.statology.org/multiple-linear-regression-by-hand/
-    const PRECISION: u32 = 63;
-    let fixed_point_chip = FixedPointChip::<F, PRECISION>::default(builder);
+    // works for any number of features: the claimed coefficients are checked against the
+    // normal equations (X^T X) b = X^T y, or the cheaper X^T (y - X b) = 0,
+    // where X includes a column of ones for the intercept
+    let mut fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    if let Some(rounding) = input.rounding {
+        fixed_point_chip = fixed_point_chip.with_rounding(rounding);
+    }
     let ctx = builder.main(0);
 
     // 1. load inputs
-    let x_values_decimal: Vec<Vec<f64>> = input.x;
-    let x_values: Vec<Vec<_>> = x_values_decimal.iter()
-    .map(|inner_vec| inner_vec.iter().map(|&val| fixed_point_chip.quantization(val)).collect()).collect();
-
-    let y_values_decimal: Vec<f64> = input.y;
-    let y_values: Vec<_> = y_values_decimal.iter().map(|&val| fixed_point_chip.quantization(val)).collect();
-
-    let coefficients: Vec<f64> = input.coefficients;
-
-
-    // 2. calculate sum((x1)^2), sum((x2)^2), sum(x1*y), sum(x2*y) and sum(x1*x2)
-    let x1 = &x_values[0];
-    let x2 = &x_values[1];
-
-    let sum_x1: AssignedValue<F> = fixed_point_chip.qsum(ctx, x1.iter().map(|&val| QuantumCell::Witness(val)));
-    let sum_x2: AssignedValue<F> = fixed_point_chip.qsum(ctx, x2.iter().map(|&val| QuantumCell::Witness(val)));
-    let sum_y: AssignedValue<F> = fixed_point_chip.qsum(ctx, y_values.iter().map(|&val| QuantumCell::Witness(val)));
-
-    let sum_x1squared = fixed_point_chip.inner_product(
+    // every value is loaded once and the same cells are used in all sums and the commitment
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.x);
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.y);
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+
+    // 2. build the design matrix [1, x1, ..., xk]
+    let design_matrix = ols::with_intercept(&fixed_point_chip, ctx, &x_values);
+
+    // 3. constrain the coefficients to solve the least squares problem
+    ols::verify(
+        &fixed_point_chip,
         ctx,
-        x1.iter().map(|&val| QuantumCell::Witness(val)),
-        x1.iter().map(|&val| QuantumCell::Witness(val)),
+        &design_matrix,
+        &y_values,
+        &coefficients,
+        tolerance,
+        &ols::DataBounds::uniform(input.x.len(), input.max_abs).with_intercept(),
+        input.mode,
     );
 
-    let sum_x2squared = fixed_point_chip.inner_product(
-        ctx,
-        x2.iter().map(|&val| QuantumCell::Witness(val)),
-        x2.iter().map(|&val| QuantumCell::Witness(val)),
-    );
+    for (i, b) in coefficients.iter().enumerate() {
+        println!("b{}: {:?}", i, fixed_point_chip.dequantization(*b.value()));
+    }
 
-    let sum_x1y = fixed_point_chip.inner_product(
-        ctx,
-        x1.iter().map(|&val| QuantumCell::Witness(val)),
-        y_values.iter().map(|&val| QuantumCell::Witness(val)),
-    );
+    // 4. commit to the dataset (the same cells used above)
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &x_values, &y_values);
+    println!("dataset commitment: {:?}", dataset_hash.value());
 
-    let sum_x2y = fixed_point_chip.inner_product(
+    // 5. goodness of fit of the claimed model
+    let goodness_of_fit = fit::goodness_of_fit(
+        &fixed_point_chip,
         ctx,
-        x2.iter().map(|&val| QuantumCell::Witness(val)),
-        y_values.iter().map(|&val| QuantumCell::Witness(val)),
+        &design_matrix,
+        &y_values,
+        &coefficients,
     );
 
-    let sum_x1x2 = fixed_point_chip.inner_product(
-        ctx,
-        x1.iter().map(|&val| QuantumCell::Witness(val)),
-        x2.iter().map(|&val| QuantumCell::Witness(val)),
-    );
-
-    // 3. calculate regression sums
-    // regression sum x1: (sum_x1squared - (sum_x1)^2/n)
-    let sqrd_sum_x1 = fixed_point_chip.qmul(ctx, sum_x1, sum_x1);
-    let length = x_values_decimal[0].len();
-    let n = QuantumCell::Witness(fixed_point_chip.quantization(length as f64));
-    let sqrd_sum_x1_div_n = fixed_point_chip.qdiv(ctx, sqrd_sum_x1, n);
-    let regression_sum_x1 = fixed_point_chip.qsub(ctx, sum_x1squared, sqrd_sum_x1_div_n);
-
-    // regression sum x2: (sum_x2squared - (sum_x2)^2/n)
-    let sqrd_sum_x2 = fixed_point_chip.qmul(ctx, sum_x2, sum_x2);
-    let sqrd_sum_x2_div_n = fixed_point_chip.qdiv(ctx, sqrd_sum_x2, n);
-    let regression_sum_x2 = fixed_point_chip.qsub(ctx, sum_x2squared, sqrd_sum_x2_div_n);
-
-    // regression sum x1y: (sum_x1y - (sum_x1*sum_y)/n)
-    let sum_x1_mul_sum_y = fixed_point_chip.qmul(ctx, sum_x1, sum_y);
-    let sum_x1_mul_sum_y_div_n = fixed_point_chip.qdiv(ctx, sum_x1_mul_sum_y, n);
-    let regression_sum_x1y = fixed_point_chip.qsub(ctx, sum_x1y, sum_x1_mul_sum_y_div_n);
-
-    // regression sum x2y: (sum_x2y - (sum_x2*sum_y)/n)
-    let sum_x2_mul_sum_y = fixed_point_chip.qmul(ctx, sum_x2, sum_y);
-    let sum_x2_mul_sum_y_div_n = fixed_point_chip.qdiv(ctx, sum_x2_mul_sum_y, n);
-    let regression_sum_x2y = fixed_point_chip.qsub(ctx, sum_x2y, sum_x2_mul_sum_y_div_n);
-
-    // regression sum x1x2: (sum_x1x2 - (sum_x1*sum_x2)/n)
-    let sum_x1_mul_sum_x2 = fixed_point_chip.qmul(ctx, sum_x1, sum_x2);
-    let sum_x1_mul_sum_x2_div_n = fixed_point_chip.qdiv(ctx, sum_x1_mul_sum_x2, n);
-    let regression_sum_x1x2 = fixed_point_chip.qsub(ctx, sum_x1x2, sum_x1_mul_sum_x2_div_n);
-
-    // 4. calculate b0 (intercept), b1 (coefficient of x1), b2 (coefficient of x2)
-    let regression_sum_x2_sum_x1y = fixed_point_chip.qmul(ctx, regression_sum_x2, regression_sum_x1y);
-    let regression_sum_x1x2_sum_x2y = fixed_point_chip.qmul(ctx, regression_sum_x1x2, regression_sum_x2y);
-    let regression_sum_x1_sum_x2 = fixed_point_chip.qmul(ctx, regression_sum_x1, regression_sum_x2);
-    let sqrd_regression_sum_x1x2 = fixed_point_chip.qmul(ctx, regression_sum_x1x2, regression_sum_x1x2);
-
-    let b1_numerator = fixed_point_chip.qsub(ctx, regression_sum_x2_sum_x1y, regression_sum_x1x2_sum_x2y);
-    let denominator = fixed_point_chip.qsub(ctx, regression_sum_x1_sum_x2, sqrd_regression_sum_x1x2);
-    let b1 = fixed_point_chip.qdiv(ctx, b1_numerator, denominator);
-
-    let regression_sum_x1_sum_x2y = fixed_point_chip.qmul(ctx, regression_sum_x1, regression_sum_x2y);
-    let regression_sum_x1x2_sum_x1y = fixed_point_chip.qmul(ctx, regression_sum_x1x2, regression_sum_x1y);
-    let b2_numerator = fixed_point_chip.qsub(ctx, regression_sum_x1_sum_x2y, regression_sum_x1x2_sum_x1y);
-    let b2 = fixed_point_chip.qdiv(ctx, b2_numerator, denominator);
-
-    let x1_mean = fixed_point_chip.qdiv(ctx, sum_x1, n);
-    let x2_mean = fixed_point_chip.qdiv(ctx, sum_x2, n);
-    let y_mean = fixed_point_chip.qdiv(ctx, sum_y, n);
-
-    let b1_x1_mean = fixed_point_chip.qmul(ctx, b1, x1_mean);
-    let b2_x2_mean = fixed_point_chip.qmul(ctx, b2, x2_mean);
-    let y_mean_minus_b1_x1_mean = fixed_point_chip.qsub(ctx, y_mean, b1_x1_mean);
-    let b0 = fixed_point_chip.qsub(ctx, y_mean_minus_b1_x1_mean, b2_x2_mean);
-
-    // 5. compare provided coefficients (in vector) with b0, b1, b2
-    let error_rate = 0.1;
-    let b0_decimal = fixed_point_chip.dequantization(*b0.value());
-    println!("b0 (intercept): {:?}", b0_decimal);
-    let b1_decimal = fixed_point_chip.dequantization(*b1.value());
-    println!("b1: {:?}", b1_decimal);
-    let b2_decimal = fixed_point_chip.dequantization(*b2.value());
-    println!("b2: {:?}", b2_decimal);
-    
-    assert!((&coefficients[0] - b0_decimal).abs() <= error_rate);
-    assert!((&coefficients[1] - b1_decimal).abs() <= error_rate);
-    assert!((&coefficients[2] - b2_decimal).abs() <= error_rate);
+    // public instances: [b0, ..., bk, tolerance, dataset commitment, rss, tss, r2, adjusted r2, rmse]
+    make_public.extend(coefficients);
+    make_public.push(tolerance);
+    make_public.push(dataset_hash);
+    make_public.extend(goodness_of_fit.to_vec());
 }
 
 fn main() {
@@ -149,8 +101,15 @@ fn main() {
     let args = Cli::parse();
 
     let now = Instant::now();
-    run(multiple_linear_regression_circuit, args);
-    
+    run_with_report(multiple_linear_regression_circuit, args, |input: &CircuitInput, instances| {
+        // the goodness-of-fit statistics are the last public instances
+        let statistics = &instances[instances.len() - fit::LABELS.len()..];
+        for (label, value) in fit::LABELS.iter().zip(statistics) {
+            let value = FixedPointChip::<Fr>::dequantize(*value, input.precision_bits);
+            println!("{}: {:?}", label, value);
+        }
+    });
+
     let elapsed = now.elapsed();
     println!("Elapsed: {:.2?}", elapsed);
-}
\ No newline at end of file
+}
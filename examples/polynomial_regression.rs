@@ -0,0 +1,100 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::fixed_point::{default_precision_bits, FixedPointChip, Rounding};
+use halo2_graph::gadget::regression::{
+    commit_dataset, default_max_abs, load_coefficients, ols, polynomial,
+};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<f64>,      // Independent variable
+    pub y: Vec<f64>,      // Dependent variable
+    pub degree: usize,    // Degree of the polynomial
+    pub coefficients: Vec<f64>, // Coefficients of x^0, ..., x^degree
+    pub tolerance: f64,   // Allowed error per normal equation
+    #[serde(default)]
+    pub mode: ols::Verification, // "normal_equations" or "residual_orthogonality"
+    #[serde(default)]
+    pub queries: Vec<f64>, // Points at which the fitted polynomial is evaluated
+    #[serde(default)]
+    pub rounding: Option<Rounding>, // "floor", "half_even" or "toward_zero", chip defaults if unset
+    #[serde(default = "default_max_abs")]
+    pub max_abs: f64, // Bound on every |x| and |y|, constrained in-circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn polynomial_regression_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let mut fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    if let Some(rounding) = input.rounding {
+        fixed_point_chip = fixed_point_chip.with_rounding(rounding);
+    }
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.x);
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.y);
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+    let queries: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.queries);
+
+    // 2. constrain the coefficients to be the least squares fit on [1, x, ..., x^degree]
+    polynomial::verify(
+        &fixed_point_chip,
+        ctx,
+        &x_values,
+        &y_values,
+        &coefficients,
+        input.degree,
+        tolerance,
+        &ols::DataBounds::uniform(1, input.max_abs),
+        input.mode,
+    );
+
+    for (i, b) in coefficients.iter().enumerate() {
+        println!("b{}: {:?}", i, fixed_point_chip.dequantization(*b.value()));
+    }
+
+    // 3. evaluate the fitted polynomial at the query points
+    let predictions: Vec<AssignedValue<F>> = queries
+        .iter()
+        .map(|q| polynomial::evaluate(&fixed_point_chip, ctx, *q, &coefficients))
+        .collect();
+    for (q, p) in input.queries.iter().zip(predictions.iter()) {
+        println!("p({:?}): {:?}", q, fixed_point_chip.dequantization(*p.value()));
+    }
+
+    // 4. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &[x_values], &y_values);
+
+    // public instances: [b0, ..., bd, tolerance, dataset commitment, queries..., predictions...]
+    make_public.extend(coefficients);
+    make_public.push(tolerance);
+    make_public.push(dataset_hash);
+    make_public.extend(queries);
+    make_public.extend(predictions);
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(polynomial_regression_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
@@ -0,0 +1,69 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::fixed_point::{default_precision_bits, FixedPointChip};
+use halo2_graph::gadget::regression::{inference, load_coefficients};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    /// Private Witnesses
+    pub coefficients: Vec<f64>, // Coefficients including intercept
+    pub salt: String,     // Blinding of the model commitment, a field element as a string
+
+    /// Public Inputs
+    pub queries: Vec<Vec<f64>>, // Rows to predict, one vector per feature
+
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn private_inference_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load the private model and the public queries
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let salt = inference::parse_salt(&input.salt).unwrap_or_else(|e| panic!("invalid `salt`: {e}"));
+    let salt = ctx.load_witness(salt);
+    let queries: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.queries);
+
+    // 2. predict y = b0 + b1 x1 + ... + bk xk for every query row
+    // public instances: [model commitment, queries..., predictions...]
+    let predictions = inference::prove_predictions(
+        &fixed_point_chip,
+        ctx,
+        &queries,
+        &coefficients,
+        salt,
+        true,
+        make_public,
+    );
+
+    println!("model commitment: {:?}", make_public[0].value());
+    for (i, p) in predictions.iter().enumerate() {
+        println!("y{}: {:?}", i, fixed_point_chip.dequantization(*p.value()));
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(private_inference_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
@@ -0,0 +1,71 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::commitment::poseidon_commit;
+use halo2_graph::gadget::decimal::Decimal;
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions,
+};
+use halo2_graph::gadget::stats::order;
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Decimal>, // Private data, numbers or exact decimal strings
+    #[serde(default = "default_quantiles")]
+    pub quantiles: Vec<f64>, // Quantiles in [0, 1] to publish, part of the circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn default_quantiles() -> Vec<f64> {
+    vec![0.5]
+}
+
+fn quantiles_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<AssignedValue<F>> = fixed_point_chip.load_decimals(ctx, &input.x);
+
+    // 2. sort the values in-circuit and read off the quantiles
+    let sorted = order::sort(&fixed_point_chip, ctx, &x_values);
+    let quantiles: Vec<AssignedValue<F>> = input
+        .quantiles
+        .iter()
+        .map(|q| order::quantile(&fixed_point_chip, ctx, &sorted, *q))
+        .collect();
+    for (q, v) in input.quantiles.iter().zip(quantiles.iter()) {
+        println!("q{:?}: {}", q, fixed_point_chip.dequantization_str(*v.value()));
+    }
+
+    // 3. commit to the dataset
+    let dataset_hash = poseidon_commit(ctx, fixed_point_chip.gate(), &x_values);
+
+    // public instances: [quantiles..., dataset commitment]
+    make_public.extend(quantiles);
+    make_public.push(dataset_hash);
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(quantiles_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
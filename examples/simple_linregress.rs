@@ -2,7 +2,10 @@ use std::time::Instant;
 
 use clap::Parser;
 use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
-use halo2_graph::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions,
+};
+use halo2_graph::gadget::regression::{check_coefficients, commit_dataset};
 use halo2_base::utils::BigPrimeField;
 use halo2_base::AssignedValue;
 use halo2_base::QuantumCell;
@@ -19,45 +22,38 @@ pub struct CircuitInput {
     pub y: Vec<f64>,
     pub a: f64, // intercept
     pub b: f64, // slope
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
 }
 
 fn linear_regression_circuit<F: BigPrimeField>(
     builder: &mut BaseCircuitBuilder<F>,
     input: CircuitInput,
-    _make_public: &mut Vec<AssignedValue<F>>,
+    make_public: &mut Vec<AssignedValue<F>>,
 ) where F: BigPrimeField {
-    const PRECISION: u32 = 63;
-    let fixed_point_chip = FixedPointChip::<F, PRECISION>::default(builder);
+    let fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
     let ctx = builder.main(0);
 
 
     // 1. load inputs
+    // every value is loaded once, so the sums and the dataset commitment use the same cells
     let x_values_decimal: Vec<f64> = input.x;
-    let x_values: Vec<_> = x_values_decimal.iter().map(|&val| fixed_point_chip.quantization(val)).collect();
+    let x_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &x_values_decimal);
     let y_values_decimal: Vec<f64> = input.y;
-    let y_values: Vec<_> = y_values_decimal.iter().map(|&val| fixed_point_chip.quantization(val)).collect();
-    let a_decimal = input.a;
-    let b_decimal = input.b;
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &y_values_decimal);
 
     // 2. compute sums (x, y, xy, x^2)
 
-    let sum_x: AssignedValue<F> = fixed_point_chip.qsum(ctx, x_values.iter().map(|&val| QuantumCell::Witness(val)));
-    let sum_y: AssignedValue<F> = fixed_point_chip.qsum(ctx, y_values.iter().map(|&val| QuantumCell::Witness(val)));
-    let sum_xy = fixed_point_chip.inner_product(
-        ctx,
-        x_values.iter().map(|&val| QuantumCell::Witness(val)),
-        y_values.iter().map(|&val| QuantumCell::Witness(val)),
-    );
-    let sum_xsquared = fixed_point_chip.inner_product(
-        ctx,
-        x_values.iter().map(|&val| QuantumCell::Witness(val)),
-        x_values.iter().map(|&val| QuantumCell::Witness(val)),
-    );
+    let sum_x: AssignedValue<F> = fixed_point_chip.qsum(ctx, x_values.iter().copied());
+    let sum_y: AssignedValue<F> = fixed_point_chip.qsum(ctx, y_values.iter().copied());
+    let sum_xy = fixed_point_chip.inner_product(ctx, x_values.iter().copied(), y_values.iter().copied());
+    let sum_xsquared = fixed_point_chip.inner_product(ctx, x_values.iter().copied(), x_values.iter().copied());
 
 
     // 3. calculate slope and intercept using zkfixedpointchip
     let length = x_values_decimal.len();
-    let n = QuantumCell::Witness(fixed_point_chip.quantization(length as f64));
+    // n is fixed by the shape of the circuit, so it is a constant rather than a free witness
+    let n = QuantumCell::Constant(fixed_point_chip.quantization(length as f64));
 
     let sum_y_sum_x2 = fixed_point_chip.qmul(ctx, sum_y, sum_xsquared);
     let sum_x_sum_xy = fixed_point_chip.qmul(ctx, sum_x, sum_xy);
@@ -73,15 +69,27 @@ fn linear_regression_circuit<F: BigPrimeField>(
     let slope_numerator = fixed_point_chip.qsub(ctx, n_sum_xy, sum_x_sum_y);
     let slope = fixed_point_chip.qdiv(ctx, slope_numerator, denominator);
 
-    // 4. compare a and b with slope and intercept
+    // 4. constrain a and b to be within error_rate of intercept and slope
     let error_rate = 0.1;
     let slope_decimal = fixed_point_chip.dequantization(*slope.value());
     println!("slope: {:?}", slope_decimal);
     let intercept_decimal = fixed_point_chip.dequantization(*intercept.value());
     println!("intercept: {:?}", intercept_decimal);
 
-    assert!((slope_decimal - b_decimal).abs() <= error_rate);
-    assert!((intercept_decimal - a_decimal).abs() <= error_rate);
+    // public instances: [a, b, error_rate, dataset commitment]
+    check_coefficients(
+        &fixed_point_chip,
+        ctx,
+        &[intercept, slope],
+        &[input.a, input.b],
+        error_rate,
+        make_public,
+    );
+
+    // 5. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &[x_values], &y_values);
+    make_public.push(dataset_hash);
+    println!("dataset commitment: {:?}", dataset_hash.value());
 }
 
 fn main() {
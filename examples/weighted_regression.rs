@@ -0,0 +1,101 @@
+use std::time::Instant;
+use clap::Parser;
+use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
+use halo2_graph::gadget::commitment::poseidon_commit;
+use halo2_graph::gadget::fixed_point::{
+    default_precision_bits, FixedPointChip, FixedPointInstructions, Rounding,
+};
+use halo2_graph::gadget::regression::{
+    commit_dataset, default_max_abs, load_coefficients, ols, wls,
+};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+
+#[allow(unused_imports)]
+use halo2_graph::scaffold::cmd::Cli;
+use halo2_graph::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub x: Vec<Vec<f64>>, // Matrix of independent variables, one vector per feature
+    pub y: Vec<f64>,      // Dependent variable
+    pub weights: Vec<f64>, // Non-negative weight per observation
+    pub coefficients: Vec<f64>, // Coefficients including intercept
+    pub tolerance: f64,   // Allowed error per normal equation
+    #[serde(default)]
+    pub public_weights: bool, // Publish the weights instead of a commitment to them
+    #[serde(default)]
+    pub rounding: Option<Rounding>, // "floor", "half_even" or "toward_zero", chip defaults if unset
+    #[serde(default = "default_max_abs")]
+    pub max_abs: f64, // Bound on every |x| and |y|, constrained in-circuit
+    #[serde(default = "default_max_abs")]
+    pub max_weight: f64, // Bound on every weight, constrained in-circuit
+    #[serde(default = "default_precision_bits")]
+    pub precision_bits: u32, // Fractional bits of the fixed point encoding (1 to 63)
+}
+
+fn weighted_regression_circuit<F: BigPrimeField>(
+    builder: &mut BaseCircuitBuilder<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) where F: BigPrimeField {
+    let mut fixed_point_chip = FixedPointChip::new(builder, input.precision_bits);
+    if let Some(rounding) = input.rounding {
+        fixed_point_chip = fixed_point_chip.with_rounding(rounding);
+    }
+    let ctx = builder.main(0);
+
+    // 1. load inputs
+    let x_values: Vec<Vec<AssignedValue<F>>> = fixed_point_chip.load_matrix(ctx, &input.x);
+    let y_values: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.y);
+    let weights: Vec<AssignedValue<F>> = fixed_point_chip.load_vector(ctx, &input.weights);
+    let coefficients = load_coefficients(&fixed_point_chip, ctx, &input.coefficients);
+    let tolerance = ctx.load_witness(fixed_point_chip.quantization(input.tolerance));
+
+    // 2. constrain (X^T W X) b = X^T W y with X = [1, x1, ..., xk]
+    let design_matrix = ols::with_intercept(&fixed_point_chip, ctx, &x_values);
+    wls::verify_normal_equations(
+        &fixed_point_chip,
+        ctx,
+        &design_matrix,
+        &y_values,
+        &weights,
+        &coefficients,
+        tolerance,
+        &wls::WeightedDataBounds {
+            data: ols::DataBounds::uniform(input.x.len(), input.max_abs).with_intercept(),
+            w: input.max_weight,
+        },
+    );
+
+    for (i, b) in coefficients.iter().enumerate() {
+        println!("b{}: {:?}", i, fixed_point_chip.dequantization(*b.value()));
+    }
+
+    // 3. commit to the dataset
+    let dataset_hash = commit_dataset(&fixed_point_chip, ctx, &x_values, &y_values);
+
+    // public instances: [b0, ..., bk, tolerance, dataset commitment, weights or weights commitment]
+    make_public.extend(coefficients);
+    make_public.push(tolerance);
+    make_public.push(dataset_hash);
+    if input.public_weights {
+        make_public.extend(weights);
+    } else {
+        let weights_hash = poseidon_commit(ctx, fixed_point_chip.gate(), &weights);
+        make_public.push(weights_hash);
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    let now = Instant::now();
+    run(weighted_regression_circuit, args);
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
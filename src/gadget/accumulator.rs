@@ -0,0 +1,253 @@
+//! Deferred rescaling for long sums of fixed-point products.
+//!
+//! `qmul` rescales every product with `signed_div_scale`, which costs two large range checks.
+//! An [`Accumulator`] instead sums the unscaled products at scale `S^2` and rescales once when it
+//! is finished. The unscaled sum must not wrap around the field, so the operands carry a proven
+//! bound on their magnitude ([`BoundedValue`], one range check per value, shared by every product
+//! the value takes part in) and the accumulator tracks the resulting bound on the sum. Circuit
+//! construction panics if that bound could reach `2^{4p}`, the largest magnitude the rescale
+//! supports.
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::{GateInstructions, RangeInstructions};
+use halo2_base::QuantumCell::{Constant, Existing};
+use halo2_base::{
+    utils::{biguint_to_fe, fe_to_biguint, BigPrimeField},
+    AssignedValue, Context,
+};
+use num_bigint::BigUint;
+use num_integer::Integer;
+
+/// A quantized value with the constrained bound `|value| <= max_abs` (both at scale `S`).
+#[derive(Clone, Debug)]
+pub struct BoundedValue<F: BigPrimeField> {
+    pub value: AssignedValue<F>,
+    pub max_abs: BigUint,
+}
+
+/// Constrains `|a| <= max_abs` with one range check and returns `a` with this bound.
+/// Panics if `max_abs` is not in the valid range of the chip.
+pub fn bound<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: AssignedValue<F>,
+    max_abs: f64,
+) -> BoundedValue<F> {
+    // quantization saturates far outside of the valid range, so this also rejects huge bounds
+    let max_abs_q = fe_to_biguint(&chip.quantization(max_abs.abs()));
+    assert!(
+        max_abs_q < chip.max_value,
+        "bound {} is outside of the valid range (-2^{}, 2^{})",
+        max_abs,
+        chip.precision_bits,
+        chip.precision_bits
+    );
+    // |a| <= max_abs <==> a + max_abs in [0, 2 max_abs + 1)
+    let shifted = chip.gate().add(ctx, a, Constant(biguint_to_fe(&max_abs_q)));
+    let range = &max_abs_q * 2u32 + 1u32;
+    if fe_to_biguint(shifted.value()) >= range {
+        log::error!(
+            "value {} is outside of its declared bound {}",
+            chip.dequantization(*a.value()),
+            max_abs
+        );
+    }
+    chip.range_gate().check_big_less_than_safe(ctx, shifted, range);
+
+    BoundedValue { value: a, max_abs: max_abs_q }
+}
+
+/// Applies [`bound`] to every value of `a`.
+pub fn bound_vector<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &[AssignedValue<F>],
+    max_abs: f64,
+) -> Vec<BoundedValue<F>> {
+    a.iter().map(|a| bound(chip, ctx, *a, max_abs)).collect()
+}
+
+/// Constrains `a` to the valid range of the chip, `|a| < 2^p`, and returns it with this bound.
+/// For values without a tighter declared bound, such as claimed coefficients.
+pub fn bound_to_valid_range<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: AssignedValue<F>,
+) -> BoundedValue<F> {
+    chip.check_valid_range(ctx, a, "bound");
+    BoundedValue { value: a, max_abs: &chip.max_value - 1u32 }
+}
+
+/// A sum of products at scale `S^2` with the bound `|sum| <= max_abs`.
+#[derive(Clone, Debug)]
+pub struct Accumulator<F: BigPrimeField> {
+    pub sum: AssignedValue<F>,
+    pub max_abs: BigUint,
+}
+
+impl<F: BigPrimeField> Accumulator<F> {
+    pub fn new(ctx: &mut Context<F>) -> Self {
+        Self { sum: ctx.load_constant(F::from(0)), max_abs: BigUint::from(0u32) }
+    }
+
+    /// Adds `a b` to the sum without rescaling.
+    pub fn add_product(
+        &mut self,
+        chip: &FixedPointChip<F>,
+        ctx: &mut Context<F>,
+        a: &BoundedValue<F>,
+        b: &BoundedValue<F>,
+    ) {
+        self.add_bound(chip, &a.max_abs * &b.max_abs);
+        self.sum = chip.gate().mul_add(ctx, a.value, b.value, self.sum);
+    }
+
+    /// Adds `a^T b` to the sum without rescaling.
+    pub fn add_inner_product(
+        &mut self,
+        chip: &FixedPointChip<F>,
+        ctx: &mut Context<F>,
+        a: &[BoundedValue<F>],
+        b: &[BoundedValue<F>],
+    ) {
+        assert_eq!(a.len(), b.len(), "inner product of vectors of different lengths");
+        self.add_bound(chip, a.iter().zip(b.iter()).map(|(a, b)| &a.max_abs * &b.max_abs).sum());
+        let products = chip.gate().inner_product(
+            ctx,
+            a.iter().map(|a| Existing(a.value)),
+            b.iter().map(|b| Existing(b.value)),
+        );
+        self.sum = chip.gate().add(ctx, self.sum, products);
+    }
+
+    /// Rescales the sum once, rounding with the chip's rounding mode. In checked mode the result
+    /// is constrained to the valid range.
+    pub fn finish(self, chip: &FixedPointChip<F>, ctx: &mut Context<F>) -> AssignedValue<F> {
+        let res = chip.rescale(ctx, self.sum);
+        chip.check_overflow(ctx, res, "accumulator");
+
+        res
+    }
+
+    fn add_bound(&mut self, chip: &FixedPointChip<F>, max_abs: BigUint) {
+        self.max_abs += max_abs;
+        // signed_div_scale needs |sum| < 2^{4p}, which is also far from the field modulus
+        assert!(
+            self.max_abs < chip.max_value.pow(2),
+            "accumulated products may reach 2^{} and wrap around, tighten the operand bounds",
+            chip.precision_bits * 4
+        );
+    }
+}
+
+/// `a b` with a single rescale. The result is bounded by the product of the bounds (rounded up),
+/// which needs no range check, so it can be an operand of further sums.
+pub fn product<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &BoundedValue<F>,
+    b: &BoundedValue<F>,
+) -> BoundedValue<F> {
+    let mut acc = Accumulator::new(ctx);
+    acc.add_product(chip, ctx, a, b);
+    // rounding to any direction stays within ceil(|a b| / S)
+    let max_abs = acc.max_abs.div_ceil(&fe_to_biguint(&chip.quantization_scale));
+
+    BoundedValue { value: acc.finish(chip, ctx), max_abs }
+}
+
+/// `a^T b` with a single rescale.
+pub fn inner_product<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &[BoundedValue<F>],
+    b: &[BoundedValue<F>],
+) -> AssignedValue<F> {
+    let mut acc = Accumulator::new(ctx);
+    acc.add_inner_product(chip, ctx, a, b);
+    acc.finish(chip, ctx)
+}
+
+/// The matrix product `A B` (row by row) of `A` given row by row and `B` given column by column,
+/// e.g. `X^T X` or `X^T y` for a column-wise design matrix `X`. Every entry is rescaled once.
+pub fn matrix_product<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a_rows: &[Vec<BoundedValue<F>>],
+    b_columns: &[Vec<BoundedValue<F>>],
+) -> Vec<Vec<AssignedValue<F>>> {
+    a_rows
+        .iter()
+        .map(|row| b_columns.iter().map(|column| inner_product(chip, ctx, row, column)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bound, bound_vector, matrix_product, Accumulator};
+    use crate::gadget::native::FixedPoint;
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    type Fixed = FixedPoint<PRECISION>;
+
+    fn samples(seed: u64, n: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| rng.gen_range(-100.0..100.0)).collect()
+    }
+
+    #[test]
+    fn test_matrix_product() {
+        // X^T X for 3 columns of 256 rows, every entry rescaled once
+        let columns: Vec<Vec<f64>> = (0..3).map(|j| samples(j, 256)).collect();
+        let gram = mock_run(|chip, ctx| {
+            let bounded: Vec<_> = columns
+                .iter()
+                .map(|column| {
+                    let column = chip.load_vector(ctx, column);
+                    bound_vector(chip, ctx, &column, 100.0)
+                })
+                .collect();
+            matrix_product(chip, ctx, &bounded, &bounded).concat()
+        });
+
+        let columns: Vec<Vec<Fixed>> =
+            columns.iter().map(|c| c.iter().map(|x| Fixed::quantize(*x)).collect()).collect();
+        for (idx, entry) in gram.iter().enumerate() {
+            let (i, j) = (idx / columns.len(), idx % columns.len());
+            let expected = Fixed::inner_product(&columns[i], &columns[j]);
+            assert_eq!(Fixed::from_field(*entry), expected, "entry ({i}, {j})");
+        }
+    }
+
+    #[test]
+    fn test_out_of_bound() {
+        assert!(!mock_verify(|chip, ctx| {
+            let a = ctx.load_witness(chip.quantization(-150.0));
+            bound(chip, ctx, a, 100.0);
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the valid range")]
+    fn test_bound_out_of_range() {
+        // |a| <= 2^32 is not representable at 32 bits of precision
+        mock_verify(|chip, ctx| {
+            let a = ctx.load_witness(chip.quantization(1.0));
+            bound(chip, ctx, a, 2f64.powi(32));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "wrap around")]
+    fn test_wraparound() {
+        // (2^30 * 2^32)^2 = 2^124 per product, so 16 products may reach 2^128
+        mock_verify(|chip, ctx| {
+            let a = ctx.load_witness(chip.quantization(1.0));
+            let a = bound(chip, ctx, a, 2f64.powi(30));
+            let mut acc = Accumulator::new(ctx);
+            for _ in 0..16 {
+                acc.add_product(chip, ctx, &a, &a);
+            }
+        });
+    }
+}
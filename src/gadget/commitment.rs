@@ -0,0 +1,26 @@
+//! Poseidon commitments to assigned values, used to tie a proof to a particular (private) input.
+//! The hash parameters are the same as in the `poseidon` and `node_connectivity` examples.
+use halo2_base::{
+    gates::GateChip, poseidon::hasher::PoseidonHasher, utils::BigPrimeField, AssignedValue,
+    Context,
+};
+use snark_verifier_sdk::halo2::OptimizedPoseidonSpec;
+
+// parameters for the Poseidon hash function
+pub const T: usize = 3;
+pub const RATE: usize = 2;
+pub const R_F: usize = 8;
+pub const R_P: usize = 57;
+
+/// Returns the Poseidon hash of `values`. The hash is computed over the given cells themselves,
+/// so the commitment is bound to exactly the values used elsewhere in the circuit.
+pub fn poseidon_commit<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    values: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let mut poseidon =
+        PoseidonHasher::<F, T, RATE>::new(OptimizedPoseidonSpec::new::<R_F, R_P, 0>());
+    poseidon.initialize_consts(ctx, gate);
+    poseidon.hash_fix_len_array(ctx, gate, values)
+}
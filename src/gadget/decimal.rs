@@ -0,0 +1,150 @@
+//! Exact decimal inputs for the `FixedPointChip`.
+//!
+//! `FixedPointChip::quantization` goes through `f64` and `u128`, so decimal inputs such as `0.1`
+//! pick up binary rounding errors and large magnitudes overflow. The helpers here parse decimal
+//! strings into exact rationals, which the chip then rounds once to the nearest quantized value.
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use serde::{Deserialize, Serialize};
+
+/// A number read from an input file: either a JSON number (quantized through `f64`, as before)
+/// or a decimal string such as `"-12.0625"` or `"1.5e-3"`, which is quantized exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Decimal {
+    Float(f64),
+    Exact(String),
+}
+
+impl From<f64> for Decimal {
+    fn from(x: f64) -> Self {
+        Decimal::Float(x)
+    }
+}
+
+impl From<&str> for Decimal {
+    fn from(s: &str) -> Self {
+        Decimal::Exact(s.to_string())
+    }
+}
+
+/// Largest magnitude of the exponent of a decimal string. Valid values are below `2^63 < 10^19`
+/// in magnitude and quantize to zero below `2^{-64}`, so larger exponents are never useful and
+/// would only make `parse_decimal` allocate huge powers of ten.
+pub const MAX_EXPONENT: u64 = 400;
+
+/// Parses a decimal string `[+-]digits[.digits][(e|E)[+-]digits]` into the exact rational
+/// `numerator / denominator`, with the denominator a power of ten. Exponents beyond
+/// `MAX_EXPONENT` in magnitude are rejected.
+pub fn parse_decimal(s: &str) -> Result<(BigInt, BigUint), String> {
+    let s = s.trim();
+    let (sign, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (Sign::Minus, rest),
+        None => (Sign::Plus, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(idx) => {
+            let exponent: i64 = unsigned[idx + 1..]
+                .parse()
+                .map_err(|_| format!("invalid exponent in decimal {s:?}"))?;
+            if exponent.unsigned_abs() > MAX_EXPONENT {
+                return Err(format!("exponent of decimal {s:?} exceeds {MAX_EXPONENT}"));
+            }
+            (&unsigned[..idx], exponent)
+        }
+        None => (unsigned, 0),
+    };
+    let (int_digits, frac_digits) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int_digits}{frac_digits}");
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("invalid decimal {s:?}"));
+    }
+    let mut numerator = BigUint::parse_bytes(digits.as_bytes(), 10).unwrap();
+    let mut denominator = BigUint::from(1u32);
+    let exponent = exponent - frac_digits.len() as i64;
+    let power = BigUint::from(10u32).pow(exponent.unsigned_abs() as u32);
+    if exponent >= 0 {
+        numerator *= power;
+    } else {
+        denominator = power;
+    }
+
+    Ok((BigInt::from_biguint(sign, numerator), denominator))
+}
+
+/// Returns `numerator / denominator * 2^precision_bits` rounded to the nearest integer,
+/// ties away from zero like `f64::round`.
+pub fn quantize_ratio(numerator: &BigInt, denominator: &BigUint, precision_bits: u32) -> BigInt {
+    assert!(*denominator != BigUint::from(0u32), "denominator must not be zero");
+    let scaled = numerator.magnitude() << precision_bits;
+    let (mut quotient, remainder) = scaled.div_rem(denominator);
+    if remainder * 2u32 >= *denominator {
+        quotient += 1u32;
+    }
+
+    BigInt::from_biguint(numerator.sign(), quotient)
+}
+
+/// Formats the quantized value `x_q` as the exact decimal `x_q / 2^precision_bits`, without
+/// trailing zeros (every dyadic fraction has a finite decimal expansion).
+pub fn format_quantized(x_q: &BigInt, precision_bits: u32) -> String {
+    let scale = BigUint::from(1u32) << precision_bits;
+    let (int_part, frac_part) = x_q.magnitude().div_rem(&scale);
+    let sign = if x_q.sign() == Sign::Minus { "-" } else { "" };
+    if frac_part == BigUint::from(0u32) {
+        return format!("{sign}{int_part}");
+    }
+    // frac / 2^p = frac * 5^p / 10^p
+    let frac_digits = (frac_part * BigUint::from(5u32).pow(precision_bits)).to_string();
+    let frac_digits = format!("{:0>width$}", frac_digits, width = precision_bits as usize);
+
+    format!("{sign}{int_part}.{}", frac_digits.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_quantized, parse_decimal, quantize_ratio};
+    use num_bigint::{BigInt, BigUint};
+
+    #[test]
+    fn test_parse_decimal() {
+        let (n, d) = parse_decimal("-12.0625").unwrap();
+        assert_eq!((n, d), (BigInt::from(-120625), BigUint::from(10000u32)));
+        let (n, d) = parse_decimal("1.5e-3").unwrap();
+        assert_eq!((n, d), (BigInt::from(15), BigUint::from(10000u32)));
+        let (n, d) = parse_decimal("+2E3").unwrap();
+        assert_eq!((n, d), (BigInt::from(2000), BigUint::from(1u32)));
+        assert!(parse_decimal("1.2.3").is_err());
+        assert!(parse_decimal("abc").is_err());
+        assert!(parse_decimal("").is_err());
+        // would truncate to 10^0 in u32 or allocate a gigabyte-sized power of ten
+        assert!(parse_decimal("1e4294967296").is_err());
+        assert!(parse_decimal("1e999999999").is_err());
+        assert!(parse_decimal("1e-999999999").is_err());
+        assert!(parse_decimal("1e-9223372036854775808").is_err());
+        assert!(parse_decimal("1e400").is_ok());
+    }
+
+    #[test]
+    fn test_quantize_exact() {
+        // 0.1 * 2^4 = 1.6 rounds to 2, -0.5 * 2^1 = -1
+        let (n, d) = parse_decimal("0.1").unwrap();
+        assert_eq!(quantize_ratio(&n, &d, 4), BigInt::from(2));
+        let (n, d) = parse_decimal("-0.5").unwrap();
+        assert_eq!(quantize_ratio(&n, &d, 1), BigInt::from(-1));
+        // far beyond u128 after scaling by 2^63
+        let (n, d) = parse_decimal("123456789012345678901234567890").unwrap();
+        let x_q = quantize_ratio(&n, &d, 63);
+        assert_eq!(x_q, BigInt::from(123456789012345678901234567890u128) << 63);
+        assert_eq!(format_quantized(&x_q, 63), "123456789012345678901234567890");
+    }
+
+    #[test]
+    fn test_format_quantized() {
+        assert_eq!(format_quantized(&BigInt::from(-193), 4), "-12.0625");
+        assert_eq!(format_quantized(&BigInt::from(48), 4), "3");
+        // 2^-63
+        let expected = "0.000000000000000000108420217248550443400745280086994171142578125";
+        assert_eq!(format_quantized(&BigInt::from(1), 63), expected);
+    }
+}
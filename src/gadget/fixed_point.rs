@@ -6,17 +6,133 @@ use halo2_base::{
 };
 use halo2_base::gates::circuit::builder::BaseCircuitBuilder;
 
-use num_bigint::BigUint;
+use super::decimal::{format_quantized, parse_decimal, quantize_ratio, Decimal};
+use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::Integer;
+use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, ops::Sub};
 
 const FIELD_BYTE_SIZE: usize = 32;
 
-/// `PRECISION_BITS` indicates the precision of integer and fractional parts.
-/// For example, `PRECISION_BITS = 32` indicates this chip implements 32.32 fixed point decimal arithmetics.
+// generated by remez algorithm, poly degree 12, precision bits: 64.28
+pub(crate) const EXP2_POLY: [f64; 13] = [
+    3.6240421303547230336183979205877e-11,
+    4.1284327467833130245549169910389e-10,
+    0.0000000071086385644026346316624185550542,
+    0.00000010172297085296590958930245291448,
+    0.0000013215904023658396206789543841996,
+    0.000015252713316417140696221389106544,
+    0.00015403531076657894204857389177279,
+    0.0013333558131297097698435464957392,
+    0.0096181291078409107025643582456283,
+    0.055504108664804181586140094858174,
+    0.24022650695910142332414229540187,
+    0.69314718055994529934452147700678,
+    1.0,
+];
+
+// generated by lolremez -d 14  -r "2:4" "log2(x)"
+// Estimated max error: 6.4897885416380772e-13
+pub(crate) const LOG2_POLY: [f64; 15] = [
+    -3.319586265362338e-08,
+    1.4957235315170112e-06,
+    -3.1350053389526744e-05,
+    0.00040554177582512901,
+    -0.0036218342998850703,
+    0.023663846121538389,
+    -0.11691877183255484,
+    0.44524062371564499,
+    -1.3195777548208449,
+    3.0518128028712077,
+    -5.4904626000399528,
+    7.6298580090181591,
+    -8.1653313719804235,
+    7.1389971101896279,
+    -3.1937385492842112,
+];
+
+// generated by lolremez -d 14  -r "0:pi" "sin(x)"
+// Estimated max error: 1.9323057584419826e-15
+pub(crate) const SIN_POLY: [f64; 15] = [
+    -1.1008071636607462e-11,
+    2.4208013888629323e-10,
+    -3.8584805817996712e-10,
+    -2.3786993104309845e-08,
+    -2.9795813710683115e-09,
+    2.7608543130047009e-06,
+    -6.4467066994122565e-09,
+    -0.00019840680551418068,
+    -3.839555844512214e-09,
+    0.0083333350601673614,
+    -5.0943769725466814e-10,
+    -0.16666666657583049,
+    -8.5029878414113731e-12,
+    1.0000000000003146,
+    -1.9323057584419828e-15,
+];
+
+// generated by remez algorithm, poly degree 18 in s = x^2 on [0, 1], atan(x) ~ x p(x^2)
+// Estimated max error: 1.8602e-16
+pub(crate) const ATAN_POLY: [f64; 19] = [
+    3.1341128643034434e-05,
+    -0.00032903697594308383,
+    0.001635295507400974,
+    -0.005154436513630724,
+    0.011692544167435578,
+    -0.020651925264762193,
+    0.03021482228452352,
+    -0.0387244190996611,
+    0.04577138417999752,
+    -0.052066128214743,
+    0.05868967043262116,
+    -0.06664268753231231,
+    0.07691991900961885,
+    -0.09090879706098262,
+    0.11111109285966995,
+    -0.14285714216323553,
+    0.19999999998603019,
+    -0.3333333333332216,
+    0.9999999999999999,
+];
+
+/// Precision used by `FixedPointChip::default`.
+pub const DEFAULT_PRECISION_BITS: u32 = 63;
+
+/// Returns `DEFAULT_PRECISION_BITS`, e.g. as serde default of a `precision_bits` input field.
+pub fn default_precision_bits() -> u32 {
+    DEFAULT_PRECISION_BITS
+}
+
+/// How `qmul` and `inner_product` round when rescaling a product, and how `qdiv` rounds the
+/// quotient. Every mode is off by less than 2^{-precision_bits}. The default is `Floor` for
+/// products and `TowardZero` for quotients, which are the cheapest modes of each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Toward negative infinity. Cheapest, but biased downward by half a unit on average.
+    #[default]
+    Floor,
+    /// To the nearest value, ties to the even one. Unbiased.
+    HalfEven,
+    /// Toward zero, i.e. the magnitude is rounded down.
+    TowardZero,
+}
+
+/// `precision_bits` indicates the precision of integer and fractional parts.
+/// For example, `precision_bits = 32` indicates this chip implements 32.32 fixed point decimal arithmetics.
 /// The valid range of the fixed point decimal is -max_value < x < max_value.
-pub struct FixedPointChip<F: BigPrimeField, const PRECISION_BITS: u32> {
+/// The precision is chosen at runtime: lower precisions need smaller range checks and hence
+/// make the circuit cheaper, at the cost of accuracy and of a smaller valid range.
+/// With `overflow_checks` set (see `with_overflow_checks`), results outside of the valid range
+/// make the circuit unsatisfiable instead of wrapping around.
+/// `rounding` and `division_rounding` (see `with_rounding`) select how products and quotients
+/// are rounded.
+pub struct FixedPointChip<F: BigPrimeField> {
     pub gate: RangeChip<F>,
+    pub precision_bits: u32,
+    pub overflow_checks: bool,
+    pub rounding: Rounding,
+    pub division_rounding: Rounding,
     pub quantization_scale: F,
     pub max_value: BigUint,
     pub bn254_max: F,
@@ -24,33 +140,27 @@ pub struct FixedPointChip<F: BigPrimeField, const PRECISION_BITS: u32> {
     pub pow_of_two: Vec<F>,
 }
 
-impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointChip<F, PRECISION_BITS> {
+impl<F: BigPrimeField> FixedPointChip<F> {
     pub fn new(
         builder: &mut BaseCircuitBuilder<F>,
+        precision_bits: u32,
     ) -> Self {
         // Note 254/4 = 63.5
-        assert!(PRECISION_BITS <= 63, "support only precision bits <= 63");
-        assert!(PRECISION_BITS >= 32, "support only precision bits >= 32");
+        assert!(precision_bits <= 63, "support only precision bits <= 63");
+        assert!(precision_bits >= 1, "support only precision bits >= 1");
 
         let gate = builder.range_chip();
         // Simple uniform symmetric quantization scheme which enforces zero point to be exactly 0
         // to reduce lots of computations.
         // Quantization: x_q = xS where S is `quantization_scale`
         // De-quantization: x = x_q / S
-        let quantization_scale = F::from_u128(2u128.pow(PRECISION_BITS as u32));
+        let quantization_scale = F::from_u128(2u128.pow(precision_bits));
         // Becuase BN254 is cyclic, negative number will be denoted as (-x) % m = m - x where m = 2^254,
         // in this chip, we treat all x > negative_point as a negative numbers.
-        // P-1 for the field
-        let bn254_max = biguint_to_fe(
-            &BigUint::parse_bytes(&F::MODULUS[2..].bytes().collect::<Vec<u8>>(), 16)
-                .unwrap()
-                .sub(1u32),
-        );
-        // -max_value % m = negative_point
-        // doesn't seem like the +1 is required here
-        let negative_point = bn254_max - F::from_u128(2u128.pow(PRECISION_BITS * 2 + 1)) + F::from(1);
+        let bn254_max = Self::field_max();
+        let negative_point = Self::negative_point(bn254_max, precision_bits);
         // min_value < x < max_value
-        let max_value = BigUint::from(2u32).pow(PRECISION_BITS * 2);
+        let max_value = BigUint::from(2u32).pow(precision_bits * 2);
 
         let mut pow_of_two = Vec::with_capacity(F::NUM_BITS as usize);
         let two = F::from(2);
@@ -62,6 +172,10 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointChip<F, PRECISION_BI
 
         Self {
             gate,
+            precision_bits,
+            overflow_checks: false,
+            rounding: Rounding::Floor,
+            division_rounding: Rounding::TowardZero,
             quantization_scale,
             max_value,
             bn254_max,
@@ -71,7 +185,157 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointChip<F, PRECISION_BI
     }
 
     pub fn default(builder: &mut BaseCircuitBuilder<F>) -> Self {
-        Self::new(builder)
+        Self::new(builder, DEFAULT_PRECISION_BITS)
+    }
+
+    /// Enables the checked mode: the results of `qadd`, `qsub`, `qsum`, `qmul`, `inner_product`
+    /// and `clip` are constrained to `(-max_value, max_value)` (`qdiv` always is).
+    /// Each check costs a range check of `2 * precision_bits + 1` bits.
+    pub fn with_overflow_checks(mut self) -> Self {
+        self.overflow_checks = true;
+        self
+    }
+
+    /// Sets the rounding mode of `qmul`, `inner_product` and `qdiv`. By default products round
+    /// with `Rounding::Floor` and quotients with `Rounding::TowardZero`; the other modes cost a
+    /// few extra range checks per rounding.
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self.division_rounding = rounding;
+        self
+    }
+
+    /// Sets the rounding mode of `qdiv` only, see `with_rounding`.
+    pub fn with_division_rounding(mut self, rounding: Rounding) -> Self {
+        self.division_rounding = rounding;
+        self
+    }
+
+    /// In checked mode, constrains `-max_value < a < max_value` and logs an error naming `op`
+    /// during witness generation if it does not hold. Does nothing otherwise.
+    pub(crate) fn check_overflow(&self, ctx: &mut Context<F>, a: AssignedValue<F>, op: &str) {
+        if self.overflow_checks {
+            self.check_valid_range(ctx, a, op);
+        }
+    }
+
+    /// Constrains `-max_value < a < max_value` regardless of the mode, e.g. for claimed values
+    /// that enter products, and logs an error naming `op` during witness generation if it does
+    /// not hold.
+    pub(crate) fn check_valid_range(&self, ctx: &mut Context<F>, a: AssignedValue<F>, op: &str) {
+        // a in (-max_value, max_value) <==> a + max_value - 1 in [0, 2 * max_value - 1)
+        let max_value_minus1 = self.max_value.clone() - BigUint::from(1u32);
+        let bound = self.max_value.clone() * BigUint::from(2u32) - BigUint::from(1u32);
+        let shifted = self.gate().add(ctx, a, Constant(biguint_to_fe(&max_value_minus1)));
+        if fe_to_biguint(shifted.value()) >= bound {
+            log::error!(
+                "fixed point overflow in {}: value is outside of (-2^{}, 2^{})",
+                op,
+                self.precision_bits,
+                self.precision_bits
+            );
+        }
+        self.range_gate().check_big_less_than_safe(ctx, shifted, bound);
+    }
+
+    /// Witnesses `(|a|, a < 0)` and constrains `a = (-1)^{a < 0} |a|` with `|a| < max_value`
+    /// and `a < 0` unset for `a = 0`.
+    /// Much cheaper than `qabs` and `is_neg`, but `a` must be in the valid range.
+    fn witness_abs(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+    ) -> (AssignedValue<F>, AssignedValue<F>) {
+        let a = self.gate().add(ctx, a, Constant(F::from(0)));
+        let a_is_neg = *a.value() > self.negative_point;
+        let a_abs = ctx.load_witness(if a_is_neg { -*a.value() } else { *a.value() });
+        let is_neg = ctx.load_witness(F::from(a_is_neg as u64));
+        self.gate().assert_bit(ctx, is_neg);
+        self.range_gate().range_check(ctx, a_abs, self.precision_bits as usize * 2);
+        let a_check = self.cond_neg(ctx, a_abs, is_neg);
+        ctx.constrain_equal(&a_check, &a);
+        // -0 = 0, so the sign of zero has to be fixed separately
+        let a_is_zero = self.gate().is_zero(ctx, a_abs);
+        let neg_zero = self.gate().and(ctx, is_neg, a_is_zero);
+        self.gate().assert_is_const(ctx, &neg_zero, &F::from(0));
+
+        (a_abs, is_neg)
+    }
+
+    /// Returns 1 if the quotient `q` of `signed_div_scale` is negative and 0 otherwise.
+    /// Since |q| < 2^{3p}, q + 2^{3p} is non-negative and below 2^{3p} iff q is negative.
+    fn quotient_is_neg(&self, ctx: &mut Context<F>, q: AssignedValue<F>) -> AssignedValue<F> {
+        let q_num_bits = self.precision_bits as usize * 3;
+        let q_offset = Constant(self.pow_of_two[q_num_bits]);
+        let q_shift = self.gate().add(ctx, q, q_offset);
+
+        self.range_gate().is_less_than(ctx, q_shift, q_offset, q_num_bits + 1)
+    }
+
+    /// Returns 1 if a quotient with remainder `rem` in `[0, divisor)` has to be rounded up to
+    /// round half to even, i.e. if `2 rem > divisor` or `2 rem = divisor` and the quotient is odd
+    /// (`q_is_odd`), and 0 otherwise. `2 rem` and `divisor` must be less than 2^{num_bits}.
+    fn half_even_carry(
+        &self,
+        ctx: &mut Context<F>,
+        rem: AssignedValue<F>,
+        divisor: impl Into<QuantumCell<F>>,
+        num_bits: usize,
+        q_is_odd: AssignedValue<F>,
+    ) -> AssignedValue<F> {
+        let divisor = divisor.into();
+        let two_rem = self.gate().add(ctx, rem, rem);
+        let above_half = self.range_gate().is_less_than(ctx, divisor, two_rem, num_bits);
+        let at_half = self.gate().is_equal(ctx, two_rem, divisor);
+        let tie_up = self.gate().and(ctx, at_half, q_is_odd);
+
+        self.gate().add(ctx, above_half, tie_up)
+    }
+
+    /// Returns floor(sqrt(a)) for an integer a < 2^{3p} and is unsatisfiable for larger a.
+    /// y is supplied by the prover and checked by y^2 <= a < (y + 1)^2, i.e. a - y^2 in [0, 2y].
+    /// Since y < 2^{3p/2}, y^2 + 2y cannot wrap around the field, while a field element that
+    /// represents a negative number is close to the modulus and fails the check.
+    fn sqrt_floor(&self, ctx: &mut Context<F>, a: AssignedValue<F>) -> AssignedValue<F> {
+        let y_native = fe_to_biguint(a.value()).sqrt();
+        let y = ctx.load_witness(biguint_to_fe(&y_native));
+        let y_num_bits = (self.precision_bits as usize * 3 + 1) / 2;
+        self.range_gate().range_check(ctx, y, y_num_bits);
+        let y_square = self.gate().mul(ctx, y, y);
+        let rem = self.gate().sub(ctx, a, y_square);
+        self.range_gate().range_check(ctx, rem, y_num_bits + 1);
+        let two_y = self.gate().add(ctx, y, y);
+        let slack = self.gate().sub(ctx, two_y, rem);
+        self.range_gate().range_check(ctx, slack, y_num_bits + 1);
+
+        y
+    }
+
+    /// sqrt(1 - a^2), which is unsatisfiable for |a| > 1. (1 - a)(1 + a) is kept at scale S^2, so
+    /// its square root is at scale S without rounding the product first, which keeps the
+    /// precision near |a| = 1.
+    fn cos_of_asin(&self, ctx: &mut Context<F>, a: QuantumCell<F>) -> AssignedValue<F> {
+        let one = Constant(self.quantization(1.0));
+        let one_minus_a = self.qsub(ctx, one, a);
+        let one_plus_a = self.qadd(ctx, one, a);
+        let one_minus_a_square = self.gate().mul(ctx, one_minus_a, one_plus_a);
+
+        self.sqrt_floor(ctx, one_minus_a_square)
+    }
+
+    // P-1 for the field
+    fn field_max() -> F {
+        biguint_to_fe(
+            &BigUint::parse_bytes(&F::MODULUS[2..].bytes().collect::<Vec<u8>>(), 16)
+                .unwrap()
+                .sub(1u32),
+        )
+    }
+
+    // -max_value % m = negative_point
+    // doesn't seem like the +1 is required here
+    fn negative_point(bn254_max: F, precision_bits: u32) -> F {
+        bn254_max - F::from_u128(2u128.pow(precision_bits * 2 + 1)) + F::from(1)
     }
 
     // Function to adjust byte slices
@@ -109,15 +373,28 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointChip<F, PRECISION_BI
     }
 
     pub fn dequantization(&self, x: F) -> f64 {
+        Self::dequantize_with(x, self.bn254_max, self.negative_point, self.quantization_scale)
+    }
+
+    /// Same as `dequantization`, but does not need a chip (and hence a circuit builder),
+    /// e.g. to report public instances after proving.
+    pub fn dequantize(x: F, precision_bits: u32) -> f64 {
+        let bn254_max = Self::field_max();
+        let negative_point = Self::negative_point(bn254_max, precision_bits);
+        let quantization_scale = F::from_u128(2u128.pow(precision_bits));
+        Self::dequantize_with(x, bn254_max, negative_point, quantization_scale)
+    }
+
+    fn dequantize_with(x: F, bn254_max: F, negative_point: F, quantization_scale: F) -> f64 {
         let mut x_mut = x;
-        let negative = if x > self.negative_point {
-            x_mut = self.bn254_max - x - F::from(1); // Shouldn't this be +F::from(1)?
+        let negative = if x > negative_point {
+            x_mut = bn254_max - x - F::from(1); // Shouldn't this be +F::from(1)?
             -1f64
         } else {
             1f64
         };
         let x_u128: u128 = x_mut.get_lower_128();
-        let quantization_scale = self.quantization_scale.get_lower_128();
+        let quantization_scale = quantization_scale.get_lower_128();
         let x_int = (x_u128 / quantization_scale) as f64;
         let x_frac = (x_u128 % quantization_scale) as f64 / quantization_scale as f64;
         let x_deq = negative * (x_int + x_frac);
@@ -125,86 +402,106 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointChip<F, PRECISION_BI
         x_deq
     }
 
+    /// Quantizes the exact rational `numerator / denominator`, rounding to the nearest value.
+    /// Unlike `quantization`, this does not go through `f64`, so it is exact for any
+    /// magnitude in the valid range.
+    pub fn quantization_ratio(&self, numerator: &BigInt, denominator: &BigUint) -> F {
+        let x_q = quantize_ratio(numerator, denominator, self.precision_bits);
+        assert!(
+            x_q.magnitude() < &self.max_value,
+            "{numerator}/{denominator} is outside of the valid range"
+        );
+        let x_q_f = biguint_to_fe::<F>(x_q.magnitude());
+        if x_q.sign() == Sign::Minus {
+            -x_q_f
+        } else {
+            x_q_f
+        }
+    }
+
+    /// Quantizes the integer `x` exactly.
+    pub fn quantization_bigint(&self, x: &BigInt) -> F {
+        self.quantization_ratio(x, &BigUint::from(1u32))
+    }
+
+    /// Quantizes a decimal string such as `"0.1"` or `"-1.5e20"` exactly, see `parse_decimal`.
+    pub fn quantization_str(&self, s: &str) -> F {
+        let (numerator, denominator) = parse_decimal(s).unwrap_or_else(|e| panic!("{e}"));
+        self.quantization_ratio(&numerator, &denominator)
+    }
+
+    /// Quantizes a number read from an input file: JSON numbers as `quantization`, decimal
+    /// strings exactly.
+    pub fn quantization_decimal(&self, x: &Decimal) -> F {
+        match x {
+            Decimal::Float(x) => self.quantization(*x),
+            Decimal::Exact(s) => self.quantization_str(s),
+        }
+    }
+
+    /// Returns the exact decimal representation of the quantized value `x`.
+    pub fn dequantization_str(&self, x: F) -> String {
+        format_quantized(&Self::signed_quantized(x, self.precision_bits), self.precision_bits)
+    }
+
+    /// Decodes the field element `x` into the signed quantized integer `x_q`: every element
+    /// greater than `negative_point` encodes the negative value `x - p`. Does not need a chip.
+    pub fn signed_quantized(x: F, precision_bits: u32) -> BigInt {
+        if x > Self::negative_point(Self::field_max(), precision_bits) {
+            BigInt::from_biguint(Sign::Minus, fe_to_biguint(&-x))
+        } else {
+            BigInt::from_biguint(Sign::Plus, fe_to_biguint(&x))
+        }
+    }
+
+    /// Quantizes `values` and loads each of them once as a witness.
+    /// The returned cells should be reused (as `Existing` cells) in every computation over the
+    /// data, so that all results are provably computed over the same values.
+    pub fn load_vector(&self, ctx: &mut Context<F>, values: &[f64]) -> Vec<AssignedValue<F>> {
+        values.iter().map(|&x| ctx.load_witness(self.quantization(x))).collect()
+    }
+
+    /// Quantizes and loads every row of `values`, see `load_vector`.
+    pub fn load_matrix(
+        &self,
+        ctx: &mut Context<F>,
+        values: &[Vec<f64>],
+    ) -> Vec<Vec<AssignedValue<F>>> {
+        values.iter().map(|row| self.load_vector(ctx, row)).collect()
+    }
+
+    /// Same as `load_vector` for numbers that may be given as decimal strings.
+    pub fn load_decimals(&self, ctx: &mut Context<F>, values: &[Decimal]) -> Vec<AssignedValue<F>> {
+        values.iter().map(|x| ctx.load_witness(self.quantization_decimal(x))).collect()
+    }
+
+    /// Same as `load_matrix` for numbers that may be given as decimal strings.
+    pub fn load_decimal_matrix(
+        &self,
+        ctx: &mut Context<F>,
+        values: &[Vec<Decimal>],
+    ) -> Vec<Vec<AssignedValue<F>>> {
+        values.iter().map(|row| self.load_decimals(ctx, row)).collect()
+    }
+
     fn generate_exp2_poly(&self) -> Vec<QuantumCell<F>> {
-        // generated by remez algorithm, poly degree 12, precision bits: 64.28
-        let coef: Vec<F> = [
-            3.6240421303547230336183979205877e-11,
-            4.1284327467833130245549169910389e-10,
-            0.0000000071086385644026346316624185550542,
-            0.00000010172297085296590958930245291448,
-            0.0000013215904023658396206789543841996,
-            0.000015252713316417140696221389106544,
-            0.00015403531076657894204857389177279,
-            0.0013333558131297097698435464957392,
-            0.0096181291078409107025643582456283,
-            0.055504108664804181586140094858174,
-            0.24022650695910142332414229540187,
-            0.69314718055994529934452147700678,
-            1.0,
-        ]
-        .into_iter()
-        .map(|c| self.quantization(c))
-        .collect();
-
-        coef.iter().map(|x| Constant(*x)).collect()
+        EXP2_POLY.iter().map(|c| Constant(self.quantization(*c))).collect()
     }
 
     fn generate_log_poly(&self) -> Vec<QuantumCell<F>> {
-        // generated by lolremez -d 14  -r "2:4" "log2(x)"
-        // Estimated max error: 6.4897885416380772e-13
-        let coef: Vec<F> = [
-            -3.319586265362338e-08,
-            1.4957235315170112e-06,
-            -3.1350053389526744e-05,
-            0.00040554177582512901,
-            -0.0036218342998850703,
-            0.023663846121538389,
-            -0.11691877183255484,
-            0.44524062371564499,
-            -1.3195777548208449,
-            3.0518128028712077,
-            -5.4904626000399528,
-            7.6298580090181591,
-            -8.1653313719804235,
-            7.1389971101896279,
-            -3.1937385492842112,
-        ]
-        .into_iter()
-        .map(|c| self.quantization(c))
-        .collect();
-
-        coef.iter().map(|x| Constant(*x)).collect()
+        LOG2_POLY.iter().map(|c| Constant(self.quantization(*c))).collect()
     }
 
     fn generate_sin_poly(&self) -> Vec<QuantumCell<F>> {
-        // generated by lolremez -d 14  -r "0:pi" "sin(x)"
-        // Estimated max error: 1.9323057584419826e-15
-        let coef: Vec<F> = [
-            -1.1008071636607462e-11,
-            2.4208013888629323e-10,
-            -3.8584805817996712e-10,
-            -2.3786993104309845e-08,
-            -2.9795813710683115e-09,
-            2.7608543130047009e-06,
-            -6.4467066994122565e-09,
-            -0.00019840680551418068,
-            -3.839555844512214e-09,
-            0.0083333350601673614,
-            -5.0943769725466814e-10,
-            -0.16666666657583049,
-            -8.5029878414113731e-12,
-            1.0000000000003146,
-            -1.9323057584419828e-15,
-        ]
-        .into_iter()
-        .map(|c| self.quantization(c))
-        .collect();
-
-        coef.iter().map(|x| Constant(*x)).collect()
+        SIN_POLY.iter().map(|c| Constant(self.quantization(*c))).collect()
+    }
+
+    fn generate_atan_poly(&self) -> Vec<QuantumCell<F>> {
+        ATAN_POLY.iter().map(|c| Constant(self.quantization(*c))).collect()
     }
 }
 
-pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
+pub trait FixedPointInstructions<F: ScalarField> {
     /// Fixed point decimal and its arithmetic functions.
     /// [ref] https://github.com/XMunkki/FixPointCS/blob/c701f57c3cfe6478d1f6fd7578ae040c59386b3d/Cpp/Fixed64.h
     /// [ref] https://github.com/abdk-consulting/abdk-libraries-solidity/blob/master/ABDKMath64x64.sol
@@ -221,6 +518,8 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
     where
         F: BigPrimeField;
 
+    /// Returns 1 if `a` is negative and 0 otherwise. Accepts unscaled products as well, i.e.
+    /// `|a| < 2^{4p}`, otherwise the circuit is unsatisfiable.
     fn is_neg(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField;
@@ -241,6 +540,7 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
     /// clip the value to ensure it's in the valid range: (-2^p, 2^p), i.e., simulate overflow
     /// Warning: assumes a < 2^{p+1},This may fail silently if a is too large
     /// (e.g., mul of two large number leads to 2^{2p}).
+    /// In checked mode, constrains a to the valid range and returns it unchanged instead.
     fn clip(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField;
@@ -325,6 +625,10 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
     where
         F: BigPrimeField;
 
+    /// a / b rounded with the chip's division rounding mode, with an error below 2^{-p}. Both
+    /// operands and the result must be in the valid range and b must not be zero, otherwise the
+    /// circuit is unsatisfiable. Callers dividing unchecked sums, which may leave the valid range,
+    /// must rescale them or bound them (see `accumulator::bound`) before the division.
     fn qdiv(
         &self,
         ctx: &mut Context<F>,
@@ -334,6 +638,11 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
     where
         F: BigPrimeField;
 
+    /// 1 / a, see `qdiv`
+    fn qrecip(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
     fn inner_product<QA>(
         &self,
         ctx: &mut Context<F>,
@@ -392,6 +701,32 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
         y
     }
 
+    /// atan(a) in (-pi/2, pi/2)
+    fn qatan(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
+    /// atan2(y, x), the angle of the point (x, y) in (-pi, pi]. x and y must not both be zero,
+    /// otherwise the circuit is unsatisfiable.
+    fn qatan2(
+        &self,
+        ctx: &mut Context<F>,
+        y: impl Into<QuantumCell<F>>,
+        x: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
+    /// asin(a) in [-pi/2, pi/2]. a must be in [-1, 1], otherwise the circuit is unsatisfiable.
+    fn qasin(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
+    /// acos(a) in [0, pi]. a must be in [-1, 1], otherwise the circuit is unsatisfiable.
+    fn qacos(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
     fn qexp(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField;
@@ -416,6 +751,84 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
         y
     }
 
+    /// sigmoid(x) = 1 / (1 + e^{-x})
+    fn qsigmoid(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
+    /// returns 1 if a < b and 0 otherwise
+    /// Both operands must be in (-2^{2p+1}, 2^{2p+1}), which includes any valid value and the
+    /// difference of two valid values. This is not constrained.
+    fn qlt(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField;
+
+    /// returns 1 if a <= b and 0 otherwise, see `qlt`
+    fn qle(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let b_lt_a = self.qlt(ctx, b, a);
+        self.gate().not(ctx, b_lt_a)
+    }
+
+    /// returns 1 if a > b and 0 otherwise, see `qlt`
+    fn qgt(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        self.qlt(ctx, b, a)
+    }
+
+    /// returns 1 if a >= b and 0 otherwise, see `qlt`
+    fn qge(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        self.qle(ctx, b, a)
+    }
+
+    /// returns 1 if |a - b| <= tol and 0 otherwise (always 0 for a negative tol)
+    fn qeq_within(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+        tol: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let tol = tol.into();
+        let diff = self.qsub(ctx, a, b);
+        let neg_tol = self.neg(ctx, tol);
+        let above_lower = self.qle(ctx, neg_tol, diff);
+        let below_upper = self.qle(ctx, diff, tol);
+        let y = self.gate().and(ctx, above_lower, below_upper);
+
+        y
+    }
+
     fn qmax(
         &self,
         ctx: &mut Context<F>,
@@ -434,6 +847,23 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
     where
         F: BigPrimeField;
 
+    /// clamps a to [lo, hi], assumes lo <= hi
+    fn qclamp(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        lo: impl Into<QuantumCell<F>>,
+        hi: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let a_lo = self.qmax(ctx, a, lo);
+        let y = self.qmin(ctx, a_lo, hi);
+
+        y
+    }
+
     fn qlog(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField;
@@ -455,6 +885,8 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
         y
     }
 
+    /// sqrt(x) rounded down, with an error in (-2^{-p}, 0]. x must be non-negative, otherwise the
+    /// circuit is unsatisfiable.
     fn qsqrt(&self, ctx: &mut Context<F>, x: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField;
@@ -464,11 +896,36 @@ pub trait FixedPointInstructions<F: ScalarField, const PRECISION_BITS: u32> {
         ctx: &mut Context<F>,
         a: impl Into<QuantumCell<F>>,
     ) -> (AssignedValue<F>, AssignedValue<F>);
+
+    /// a / 2^p rounded with the chip's rounding mode, e.g. to rescale a product of two
+    /// quantized values. `signed_div_scale` is the floor variant, which also returns the remainder.
+    fn rescale(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// The largest integer <= a, as a fixed-point value.
+    fn qfloor(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// The smallest integer >= a, as a fixed-point value.
+    fn qceil(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// The integer nearest to a, ties away from zero like `f64::round`, as a fixed-point value.
+    fn qround(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// The integer part of a, i.e. a rounded toward zero, as a fixed-point value.
+    fn qtrunc(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// a - floor(a) in [0, 1), also for negative a.
+    fn qfrac(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// floor(a) as a plain integer, negative integers n are encoded as p - |n|. Round with
+    /// `qceil`, `qround` or `qtrunc` first for the other conversions.
+    fn to_integer(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
+
+    /// The fixed-point value of the plain integer n (negative integers encoded as p - |n|).
+    /// |n| must be below 2^p, otherwise the circuit is unsatisfiable.
+    fn from_integer(&self, ctx: &mut Context<F>, n: impl Into<QuantumCell<F>>) -> AssignedValue<F>;
 }
 
-impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PRECISION_BITS>
-    for FixedPointChip<F, PRECISION_BITS>
-{
+impl<F: BigPrimeField> FixedPointInstructions<F> for FixedPointChip<F> {
     type Gate = GateChip<F>;
     type RangeGate = RangeChip<F>;
 
@@ -492,7 +949,10 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     where
         F: BigPrimeField,
     {
-        self.gate().add(ctx, a, b)
+        let res = self.gate().add(ctx, a, b);
+        self.check_overflow(ctx, res, "qadd");
+
+        res
     }
 
     /// Subtracts the given numbers and returns the result
@@ -506,7 +966,20 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     where
         F: BigPrimeField,
     {
-        self.gate().sub(ctx, a, b)
+        let res = self.gate().sub(ctx, a, b);
+        self.check_overflow(ctx, res, "qsub");
+
+        res
+    }
+
+    fn qsum<Q>(&self, ctx: &mut Context<F>, a: impl IntoIterator<Item = Q>) -> AssignedValue<F>
+    where
+        Q: Into<QuantumCell<F>>,
+    {
+        let res = self.gate().sum(ctx, a);
+        self.check_overflow(ctx, res, "qsum");
+
+        res
     }
 
     // Calls is_neg; Is very expensive
@@ -522,21 +995,23 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         a_abs
     }
 
-    // Is very expensive
+    // Is expensive: a (4p+1)-bit division
     fn is_neg(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField,
     {
-        let a = a.into();
-        let a_num_bits = 254;
-        let (a_shift, _) = self.range_gate().div_mod(
+        // |a| < 2^{4p} like the unscaled products in signed_div_scale, so a + 2^{4p} is in
+        // [0, 2^{4p+1}) and its quotient by 2^{4p} is zero iff a is negative
+        let a_num_bits = self.precision_bits as usize * 4 + 1;
+        let offset = Constant(self.pow_of_two[a_num_bits - 1]);
+        let a_shift = self.gate().add(ctx, a, offset);
+        let (q, _) = self.range_gate().div_mod(
             ctx,
-            a,
-            BigUint::from(2u32).pow((PRECISION_BITS * 2 + 1) as u32),
+            a_shift,
+            BigUint::from(2u32).pow(self.precision_bits * 4),
             a_num_bits,
         );
-        let is_pos = self.gate().is_zero(ctx, a_shift);
-        let is_neg = self.gate().not(ctx, is_pos);
+        let is_neg = self.gate().is_zero(ctx, q);
 
         is_neg
     }
@@ -580,12 +1055,17 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         F: BigPrimeField,
     {
         let a = a.into();
+        if self.overflow_checks {
+            let a = self.gate().add(ctx, a, Constant(F::from(0)));
+            self.check_overflow(ctx, a, "clip");
+            return a;
+        }
         let sign = self.is_neg(ctx, a);
         let a_abs = self.qabs(ctx, a);
-        let a_num_bits = 254;
+        let a_num_bits = self.precision_bits as usize * 4;
         let m = self.max_value.clone();
         // clipped = a % m
-        // TODO (Wentao XIAO) should we just throw panic when overflow?
+        // use the checked mode (`with_overflow_checks`) to reject overflows instead
         let (_, unsigned_cliped) = self.range_gate().div_mod(ctx, a_abs, m, a_num_bits);
         let clipped = self.cond_neg(ctx, unsigned_cliped, sign);
 
@@ -628,7 +1108,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         // "quantised" ab above is = (ab).S^2
         // need to divide this by S to get the correct result
         // This step can be batched for multiple multiplications- like inner product
-        let (res, _) = self.signed_div_scale(ctx, ab);
+        let res = self.rescale(ctx, ab);
+        self.check_overflow(ctx, res, "qmul");
 
         res
     }
@@ -649,8 +1130,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         let b_sign = self.is_neg(ctx, b);
         self.gate().assert_is_const(ctx, &b_sign, &F::from(0));
         let a_abs = self.qabs(ctx, a);
-        let a_num_bits = PRECISION_BITS as usize * 4;
-        let b_num_bits = PRECISION_BITS as usize * 2;
+        let a_num_bits = self.precision_bits as usize * 4;
+        let b_num_bits = self.precision_bits as usize * 2;
         let (_, res_abs) = self.range_gate().div_mod_var(ctx, a_abs, b, a_num_bits, b_num_bits);
         let res_abs_comp = self.gate().sub(ctx, b, res_abs);
         let res = self.gate().select(ctx, res_abs_comp, res_abs, a_sign);
@@ -658,6 +1139,11 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         res
     }
 
+    // The quotient q = |a| S / |b| is supplied by the prover and checked by q |b| + r = |a| S with
+    // 0 <= r < |b|. q, r and |b| are range checked to 2p bits, so q |b| + r < 2^{4p+1} cannot wrap
+    // around the field. The signs come from `witness_abs`, which avoids the 254-bit divisions of
+    // `is_neg` and `qabs`. q rounds the magnitude down, i.e. toward zero, which is the default;
+    // the other rounding modes add a carry bit to it before the sign is applied.
     fn qdiv(
         &self,
         ctx: &mut Context<F>,
@@ -667,24 +1153,65 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     where
         F: BigPrimeField,
     {
-        let a = a.into();
-        let b = b.into();
-        let a_sign = self.is_neg(ctx, a);
-        let b_sign = self.is_neg(ctx, b);
-        let a_abs = self.qabs(ctx, a);
-        let b_abs = self.qabs(ctx, b);
-        // Because a_rescale \in [0, 2^{4p}) and b \in [0, 2^p)
-        let a_num_bits = PRECISION_BITS as usize * 4;
-        let b_num_bits = PRECISION_BITS as usize * 2;
+        let (a_abs, a_sign) = self.witness_abs(ctx, a);
+        let (b_abs, b_sign) = self.witness_abs(ctx, b);
         let a_rescale = self.gate().mul(ctx, a_abs, Constant(self.quantization_scale));
-        let (res_abs, _) =
-            self.range_gate().div_mod_var(ctx, a_rescale, b_abs, a_num_bits, b_num_bits);
+        let b_abs_native = fe_to_biguint(b_abs.value());
+        // b = 0 is left to fail the check r < |b|
+        let (res_abs_native, rem_native) = if b_abs_native == BigUint::from(0u32) {
+            (BigUint::from(0u32), BigUint::from(0u32))
+        } else {
+            fe_to_biguint(a_rescale.value()).div_rem(&b_abs_native)
+        };
+        let res_abs = ctx.load_witness(biguint_to_fe(&res_abs_native));
+        let rem = ctx.load_witness(biguint_to_fe(&rem_native));
+        let num_bits = self.precision_bits as usize * 2;
+        self.range_gate().range_check(ctx, res_abs, num_bits);
+        self.range_gate().range_check(ctx, rem, num_bits);
+        // r < |b| <==> |b| - r - 1 >= 0
+        let slack = self.gate().sub(ctx, b_abs, rem);
+        let slack = self.gate().sub(ctx, slack, Constant(F::from(1)));
+        self.range_gate().range_check(ctx, slack, num_bits);
+        let a_rescale_check = self.gate().mul_add(ctx, res_abs, b_abs, rem);
+        ctx.constrain_equal(&a_rescale_check, &a_rescale);
         let ab_sign = self.bit_xor(ctx, a_sign, b_sign);
+        let carry = match self.division_rounding {
+            Rounding::TowardZero => None,
+            // rounding the magnitude of a negative quotient up rounds it down
+            Rounding::Floor => {
+                let rem_is_zero = self.gate().is_zero(ctx, rem);
+                let rem_is_non_zero = self.gate().not(ctx, rem_is_zero);
+                Some(self.gate().and(ctx, ab_sign, rem_is_non_zero))
+            }
+            Rounding::HalfEven => {
+                let (_, res_abs_is_odd) = self.range_gate().div_mod(ctx, res_abs, 2u32, num_bits);
+                Some(self.half_even_carry(ctx, rem, b_abs, num_bits + 1, res_abs_is_odd))
+            }
+        };
+        let res_abs = match carry {
+            Some(carry) => {
+                // a carry can push the magnitude to max_value
+                let res_abs = self.gate().add(ctx, res_abs, carry);
+                self.range_gate().range_check(ctx, res_abs, num_bits);
+                res_abs
+            }
+            None => res_abs,
+        };
+        // res_abs < max_value already, so no overflow check is needed
         let res = self.cond_neg(ctx, res_abs, ab_sign);
 
         res
     }
 
+    fn qrecip(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        // |1 / a| < 2^p unless a = +-2^{-p}, so the result is in range for almost every a
+        let one = Constant(self.quantization(1.0));
+        self.qdiv(ctx, one, a)
+    }
+
     fn polynomial<QA>(
         &self,
         ctx: &mut Context<F>,
@@ -723,7 +1250,7 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     ) where
         F: BigPrimeField,
     {
-        let range_bits = PRECISION_BITS as usize * 2;
+        let range_bits = self.precision_bits as usize * 2;
         let bits = self.gate().num_to_bits(ctx, pow2_exponent, range_bits);
         let sum_of_bits = self.gate().sum(ctx, bits.clone());
         let sum_of_bits_m1 = self.gate().sub(ctx, sum_of_bits, Constant(F::from(1)));
@@ -743,8 +1270,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     {
         let a = a.into();
         let a_abs = self.qabs(ctx, a);
-        let num_bits = PRECISION_BITS as usize * 2;
-        let shift = 2u128.pow(PRECISION_BITS);
+        let num_bits = self.precision_bits as usize * 2;
+        let shift = 2u128.pow(self.precision_bits);
         let (int_part, frac_part) =
             self.range_gate().div_mod(ctx, Existing(a_abs), shift, num_bits);
         // int_part must be small as large number leads to overflow.
@@ -773,7 +1300,7 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         let is_zero = self.gate().is_zero(ctx, a_assigned);
         let is_invalid = self.gate().or(ctx, is_neg, is_zero);
         self.gate().assert_is_const(ctx, &is_invalid, &F::from(0));
-        let num_bits = (PRECISION_BITS * 2) as usize;
+        let num_bits = (self.precision_bits * 2) as usize;
         let num_digits = a_assigned
             .value()
             .to_repr()
@@ -798,7 +1325,7 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         self.gate().assert_is_const(ctx, &a_bound, &F::from(1));
 
         // shift a to ensure a = 2^m * k, m \in Z, 2^{1} <= k < 2^{2}
-        let shift = self.gate().sub(ctx, Constant(F::from(PRECISION_BITS as u64 + 2)), exp2);
+        let shift = self.gate().sub(ctx, Constant(F::from(self.precision_bits as u64 + 2)), exp2);
         let is_shift_neg = self.is_neg(ctx, shift);
         let shift_abs = self.qabs(ctx, shift);
         let shift_pow2 = self.gate().pow_of_two()[shift_abs.value().get_lower_32() as usize];
@@ -810,7 +1337,7 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
             a,
             shift_pow2_witness,
             num_bits,
-            PRECISION_BITS as usize + 1,
+            self.precision_bits as usize + 1,
         );
         let a_norm = self.gate().select(ctx, a_rs, a_ls, is_shift_neg);
 
@@ -903,7 +1430,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
             res_s = self.gate().mul_add(ctx, *ai, *bi, res_s);
         }
         // Implementing this way allows us to amortize the cost of calling this expensive rescaling- will also lead to more accuracy
-        let (res, _) = self.signed_div_scale(ctx, res_s);
+        let res = self.rescale(ctx, res_s);
+        self.check_overflow(ctx, res, "inner_product");
         res
     }
 
@@ -949,6 +1477,121 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         y
     }
 
+    fn qatan(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let one = Constant(self.quantization(1.0));
+        self.qatan2(ctx, a, one)
+    }
+
+    // The ratio r = min(|x|, |y|) / max(|x|, |y|) is in [0, 1], where atan(r) = r p(r^2) with
+    // `ATAN_POLY`. The angle is then moved to its octant (atan(1 / r) = pi/2 - atan(r)) and to its
+    // quadrant by the signs of x and y.
+    fn qatan2(
+        &self,
+        ctx: &mut Context<F>,
+        y: impl Into<QuantumCell<F>>,
+        x: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let (y_abs, y_is_neg) = self.witness_abs(ctx, y);
+        let (x_abs, x_is_neg) = self.witness_abs(ctx, x);
+        let x_lt_y = self.qlt(ctx, x_abs, y_abs);
+        let num = self.gate().select(ctx, x_abs, y_abs, x_lt_y);
+        let den = self.gate().select(ctx, y_abs, x_abs, x_lt_y);
+        let ratio = self.qdiv(ctx, num, den);
+        let ratio_square = self.qmul(ctx, ratio, ratio);
+        let coef = self.generate_atan_poly();
+        let atan_ratio_div_ratio = self.polynomial(ctx, ratio_square, coef);
+        let atan_ratio = self.qmul(ctx, ratio, atan_ratio_div_ratio);
+        // angle of (|x|, |y|)
+        let half_pi = Constant(self.quantization(std::f64::consts::FRAC_PI_2));
+        let atan_inv_ratio = self.qsub(ctx, half_pi, atan_ratio);
+        let angle = self.gate().select(ctx, atan_inv_ratio, atan_ratio, x_lt_y);
+        // angle of (x, |y|)
+        let pi = Constant(self.quantization(std::f64::consts::PI));
+        let pi_minus_angle = self.qsub(ctx, pi, angle);
+        let angle = self.gate().select(ctx, pi_minus_angle, angle, x_is_neg);
+        let y = self.cond_neg(ctx, angle, y_is_neg);
+
+        y
+    }
+
+    // asin(a) = atan2(a, sqrt(1 - a^2)), see `cos_of_asin`
+    fn qasin(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let a = a.into();
+        let cos = self.cos_of_asin(ctx, a);
+        let y = self.qatan2(ctx, a, cos);
+
+        y
+    }
+
+    // acos(a) = atan2(sqrt(1 - a^2), a)
+    fn qacos(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let a = a.into();
+        let sin = self.cos_of_asin(ctx, a);
+        let y = self.qatan2(ctx, sin, a);
+
+        y
+    }
+
+    // sigmoid(x) = 1 / (1 + e^{-|x|}) for x >= 0 and sigmoid(x) = 1 - sigmoid(|x|) for x < 0.
+    // e^{-|x|} = 2^z with z = -|x| / ln(2) is computed by `qexp2` as the reciprocal of 2^{-z},
+    // which leaves the valid range for -z >= p. z is clamped to 1 - p instead: 2^{1-p} is two
+    // units in the last place, so for |x| > (p - 1) ln(2) the result is within two units of 0 or 1.
+    fn qsigmoid(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let a = a.into();
+        let is_neg = self.is_neg(ctx, a);
+        let neg_a = self.neg(ctx, a);
+        // -|a|
+        let neg_a_abs = self.gate().select(ctx, a, neg_a, is_neg);
+        let ln2 = ctx.load_constant(self.quantization(2.0f64.ln()));
+        let z = self.qdiv(ctx, neg_a_abs, ln2);
+        let min_z = Constant(self.quantization(1.0 - self.precision_bits as f64));
+        let z = self.qmax(ctx, z, min_z);
+        let exp_neg_a_abs = self.qexp2(ctx, z);
+        let one = Constant(self.quantization(1.0));
+        let denom = self.qadd(ctx, one, exp_neg_a_abs);
+        let y_pos = self.qdiv(ctx, one, denom);
+        let y_neg = self.qsub(ctx, one, y_pos);
+        let y = self.gate().select(ctx, y_neg, y_pos, is_neg);
+
+        y
+    }
+
+    // Shifts both operands by 2^{2p+1} into [0, 2^{2p+2}) and compares them as unsigned
+    // integers, which only needs a (2p+2)-bit range check instead of the 254-bit division of
+    // `is_neg`
+    fn qlt(
+        &self,
+        ctx: &mut Context<F>,
+        a: impl Into<QuantumCell<F>>,
+        b: impl Into<QuantumCell<F>>,
+    ) -> AssignedValue<F>
+    where
+        F: BigPrimeField,
+    {
+        let offset = Constant(self.pow_of_two[self.precision_bits as usize * 2 + 1]);
+        let a_shift = self.gate().add(ctx, a, offset);
+        let b_shift = self.gate().add(ctx, b, offset);
+        let num_bits = self.precision_bits as usize * 2 + 2;
+        let y = self.range_gate().is_less_than(ctx, a_shift, b_shift, num_bits);
+
+        y
+    }
+
     fn qmax(
         &self,
         ctx: &mut Context<F>,
@@ -960,9 +1603,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     {
         let a = a.into();
         let b = b.into();
-        let amb = self.qsub(ctx, a, b);
-        let sign_amb = self.is_neg(ctx, amb);
-        let y = self.gate().select(ctx, b, a, sign_amb);
+        let a_lt_b = self.qlt(ctx, a, b);
+        let y = self.gate().select(ctx, b, a, a_lt_b);
 
         y
     }
@@ -978,9 +1620,8 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
     {
         let a = a.into();
         let b = b.into();
-        let amb = self.qsub(ctx, a, b);
-        let sign_amb = self.is_neg(ctx, amb);
-        let y = self.gate().select(ctx, a, b, sign_amb);
+        let a_lt_b = self.qlt(ctx, a, b);
+        let y = self.gate().select(ctx, a, b, a_lt_b);
 
         y
     }
@@ -997,13 +1638,15 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         y
     }
 
-    // Could be better to just verify y*y = x
+    // sqrt(x) S = sqrt(x S), see `sqrt_floor`
     fn qsqrt(&self, ctx: &mut Context<F>, x: impl Into<QuantumCell<F>>) -> AssignedValue<F>
     where
         F: BigPrimeField,
     {
-        let half = ctx.load_constant(self.quantization(0.5));
-        self.qpow(ctx, x, half)
+        let x_rescale = self.gate().mul(ctx, x, Constant(self.quantization_scale));
+        let y = self.sqrt_floor(ctx, x_rescale);
+
+        y
     }
 
     /// This shifts things by 2^precision to the right- useful after multiplying two things
@@ -1016,14 +1659,10 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         let a = a.into();
         // b = 2^p
         let b = fe_to_biguint(&self.quantization_scale);
-        // 252 = 63 * 4
-        // the raw result of multiplying two positive or negative numbers is at most 252 bit
-        // similarly the raw result of multiplying two oppositely signed numbers is p - (something 252 bit)
-        // p is at least 254 bit- I think
-        // 2^254-2^252 > 2^252
+        // the raw product of two values in the valid range is below 2^{4p} in magnitude, so a
+        // negative product is m - (something below 2^{4p}) with the field modulus m > 2^{4p+1}
         // is_neg only on BigInt - so not costly yet
-        // because of the above reasoning this correctly calculates whether a is supposed to be negative or not
-        let a_is_neg = fe_to_biguint(a.value()) > BigUint::from(2u32).pow(252u32);
+        let a_is_neg = fe_to_biguint(a.value()) >= BigUint::from(2u32).pow(self.precision_bits * 4);
         let (q, r) = if a_is_neg {
             let a_abs = fe_to_biguint(&(self.bn254_max - a.value() + F::from(1)));
             let q = fe_to_biguint(&self.bn254_max) - a_abs.div_ceil(&b) + BigUint::from(1u32);
@@ -1053,7 +1692,7 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
         self.range_gate().check_big_less_than_safe(ctx, rem, b);
         // a < 2^{4p}, b = 2^p, so |q| < 2^{3p}
         // abs_bound = 2^{3P}
-        let abs_bound = BigUint::from(2u32).pow(PRECISION_BITS * 3 as u32);
+        let abs_bound = BigUint::from(2u32).pow(self.precision_bits * 3);
         // abs_bound_minus1 = 2^{3P} - 1
         let abs_bound_minus1 = abs_bound.clone() - BigUint::from(1u32);
         // new_bound = 2*2^{3P} - 1
@@ -1068,4 +1707,107 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> FixedPointInstructions<F, PREC
 
         (div, rem)
     }
+
+    // signed_div_scale returns q = floor(a / S) and r in [0, S), the other modes add a carry bit
+    // to q. Since |q| < 2^{3p}, q + 2^{3p} is non-negative and has the parity of q.
+    fn rescale(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let (q, rem) = self.signed_div_scale(ctx, a);
+        let q_num_bits = self.precision_bits as usize * 3;
+        let q_offset = Constant(self.pow_of_two[q_num_bits]);
+        let carry = match self.rounding {
+            Rounding::Floor => return q,
+            // floor(a / S) + 1 for negative a with a non-zero remainder
+            Rounding::TowardZero => {
+                let q_is_neg = self.quotient_is_neg(ctx, q);
+                let rem_is_zero = self.gate().is_zero(ctx, rem);
+                let rem_is_non_zero = self.gate().not(ctx, rem_is_zero);
+                self.gate().and(ctx, q_is_neg, rem_is_non_zero)
+            }
+            Rounding::HalfEven => {
+                let q_shift = self.gate().add(ctx, q, q_offset);
+                let (_, q_is_odd) = self.range_gate().div_mod(ctx, q_shift, 2u32, q_num_bits + 1);
+                let scale = Constant(self.quantization_scale);
+                let precision_bits = self.precision_bits as usize;
+                self.half_even_carry(ctx, rem, scale, precision_bits + 1, q_is_odd)
+            }
+        };
+
+        self.gate().add(ctx, q, carry)
+    }
+
+    // a = q S + r with r in [0, S) (see `signed_div_scale`), so floor(a) = q S = a - r
+    fn qfloor(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let a = a.into();
+        let (_, rem) = self.signed_div_scale(ctx, a);
+        let y = self.gate().sub(ctx, a, rem);
+
+        y
+    }
+
+    // floor(a) + 1 unless a is an integer
+    fn qceil(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let a = a.into();
+        let (_, rem) = self.signed_div_scale(ctx, a);
+        let floor = self.gate().sub(ctx, a, rem);
+        let rem_is_zero = self.gate().is_zero(ctx, rem);
+        let rem_is_non_zero = self.gate().not(ctx, rem_is_zero);
+        let y = self.gate().mul_add(ctx, rem_is_non_zero, Constant(self.quantization_scale), floor);
+
+        y
+    }
+
+    // floor(a) + 1 if the fractional part is above 1/2, or exactly 1/2 and a is non-negative
+    fn qround(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let a = a.into();
+        let (q, rem) = self.signed_div_scale(ctx, a);
+        let floor = self.gate().sub(ctx, a, rem);
+        let precision_bits = self.precision_bits as usize;
+        let half = Constant(self.pow_of_two[precision_bits - 1]);
+        let above_half = self.range_gate().is_less_than(ctx, half, rem, precision_bits);
+        let at_half = self.gate().is_equal(ctx, rem, half);
+        let q_is_neg = self.quotient_is_neg(ctx, q);
+        let q_is_non_neg = self.gate().not(ctx, q_is_neg);
+        let tie_up = self.gate().and(ctx, at_half, q_is_non_neg);
+        let carry = self.gate().add(ctx, above_half, tie_up);
+        let y = self.gate().mul_add(ctx, carry, Constant(self.quantization_scale), floor);
+
+        y
+    }
+
+    // floor(a) + 1 for negative a that is not an integer
+    fn qtrunc(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let a = a.into();
+        let (q, rem) = self.signed_div_scale(ctx, a);
+        let floor = self.gate().sub(ctx, a, rem);
+        let q_is_neg = self.quotient_is_neg(ctx, q);
+        let rem_is_zero = self.gate().is_zero(ctx, rem);
+        let rem_is_non_zero = self.gate().not(ctx, rem_is_zero);
+        let carry = self.gate().and(ctx, q_is_neg, rem_is_non_zero);
+        let y = self.gate().mul_add(ctx, carry, Constant(self.quantization_scale), floor);
+
+        y
+    }
+
+    fn qfrac(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let (_, rem) = self.signed_div_scale(ctx, a);
+
+        rem
+    }
+
+    fn to_integer(&self, ctx: &mut Context<F>, a: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let (q, _) = self.signed_div_scale(ctx, a);
+
+        q
+    }
+
+    // |n| < 2^p <==> n + 2^p - 1 in [0, 2^{p+1} - 1), which keeps n S in the valid range
+    fn from_integer(&self, ctx: &mut Context<F>, n: impl Into<QuantumCell<F>>) -> AssignedValue<F> {
+        let n = self.gate().add(ctx, n, Constant(F::from(0)));
+        let shifted = self.gate().add(ctx, n, Constant(self.quantization_scale - F::from(1)));
+        let bound = BigUint::from(2u32).pow(self.precision_bits + 1) - 1u32;
+        self.range_gate().check_big_less_than_safe(ctx, shifted, bound);
+        let y = self.gate().mul(ctx, n, Constant(self.quantization_scale));
+
+        y
+    }
 }
\ No newline at end of file
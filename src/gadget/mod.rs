@@ -1 +1,9 @@
-pub mod fixed_point;
\ No newline at end of file
+pub mod accumulator;
+pub mod commitment;
+pub mod decimal;
+pub mod fixed_point;
+pub mod native;
+pub mod regression;
+pub mod stats;
+#[cfg(test)]
+pub(crate) mod testing;
\ No newline at end of file
@@ -0,0 +1,920 @@
+//! Native emulation of the `FixedPointChip`.
+//!
+//! `FixedPoint<PRECISION>` holds the signed quantized integer `x * 2^PRECISION` and implements
+//! the same quantization, rescaling, division and polynomial approximations as the chip with
+//! `precision_bits = PRECISION`. For inputs in the valid range its results are exactly the values
+//! assigned by the chip, so it can be used to predict circuit outputs, measure precision loss
+//! against `f64` and compute expected values in tests. `qmul`, `qdiv` and `inner_product` round
+//! like a chip with the default rounding modes (`Rounding::Floor` for products and
+//! `Rounding::TowardZero` for quotients), the `*_with` variants take the rounding mode and so do
+//! the composite functions (`qexp`, `qsin`, ...) only in the default modes.
+use super::fixed_point::{FixedPointChip, Rounding, ATAN_POLY, EXP2_POLY, LOG2_POLY, SIN_POLY};
+use halo2_base::utils::{biguint_to_fe, BigPrimeField};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedPoint<const PRECISION: u32> {
+    raw: BigInt,
+}
+
+impl<const PRECISION: u32> FixedPoint<PRECISION> {
+    /// Wraps the quantized value `x * 2^PRECISION`.
+    pub fn from_raw(raw: BigInt) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the quantized value `x * 2^PRECISION`.
+    pub fn raw(&self) -> &BigInt {
+        &self.raw
+    }
+
+    /// 2^PRECISION
+    pub fn scale() -> BigInt {
+        BigInt::from(1u32) << PRECISION
+    }
+
+    /// 2^{2 PRECISION}, the valid range is -max_value < x < max_value.
+    pub fn max_value() -> BigInt {
+        BigInt::from(1u32) << (2 * PRECISION)
+    }
+
+    pub fn zero() -> Self {
+        Self::from_raw(BigInt::from(0u32))
+    }
+
+    pub fn one() -> Self {
+        Self::from_raw(Self::scale())
+    }
+
+    /// Same as `FixedPointChip::quantization`.
+    pub fn quantize(x: f64) -> Self {
+        let x_q = (x.abs() * 2u128.pow(PRECISION) as f64).round() as u128;
+        let sign = if x.signum() < 0.0 { Sign::Minus } else { Sign::Plus };
+        Self::from_raw(BigInt::from_biguint(sign, BigUint::from(x_q)))
+    }
+
+    /// Returns `x_q / 2^PRECISION` as `f64`.
+    pub fn to_f64(&self) -> f64 {
+        let raw: f64 = self.raw.to_string().parse().unwrap();
+        raw / 2f64.powi(PRECISION as i32)
+    }
+
+    /// Encodes the value as a field element, negative values as `p - |x_q|`.
+    pub fn to_field<F: BigPrimeField>(&self) -> F {
+        let abs = biguint_to_fe::<F>(self.raw.magnitude());
+        if self.is_neg() {
+            -abs
+        } else {
+            abs
+        }
+    }
+
+    /// Decodes a field element assigned by the chip, see `FixedPointChip::signed_quantized`.
+    pub fn from_field<F: BigPrimeField>(x: F) -> Self {
+        Self::from_raw(FixedPointChip::signed_quantized(x, PRECISION))
+    }
+
+    pub fn is_in_range(&self) -> bool {
+        self.raw.magnitude() < Self::max_value().magnitude()
+    }
+
+    /// Same as `FixedPointInstructions::signed_div_scale`: `floor(a / 2^PRECISION)`.
+    pub fn signed_div_scale(a: &BigInt) -> BigInt {
+        a.div_floor(&Self::scale())
+    }
+
+    /// Same as `FixedPointInstructions::rescale`: `a / 2^PRECISION` rounded with `rounding`.
+    pub fn rescale(a: &BigInt, rounding: Rounding) -> BigInt {
+        round_div(a, &Self::scale(), rounding)
+    }
+
+    pub fn is_neg(&self) -> bool {
+        self.raw.sign() == Sign::Minus
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::from_raw(-&self.raw)
+    }
+
+    pub fn qabs(&self) -> Self {
+        Self::from_raw(BigInt::from_biguint(Sign::Plus, self.raw.magnitude().clone()))
+    }
+
+    pub fn qadd(&self, b: &Self) -> Self {
+        Self::from_raw(&self.raw + &b.raw)
+    }
+
+    pub fn qsub(&self, b: &Self) -> Self {
+        Self::from_raw(&self.raw - &b.raw)
+    }
+
+    pub fn qsum<'a>(a: impl IntoIterator<Item = &'a Self>) -> Self {
+        Self::from_raw(a.into_iter().map(|a| &a.raw).sum())
+    }
+
+    pub fn qmul(&self, b: &Self) -> Self {
+        self.qmul_with(b, Rounding::Floor)
+    }
+
+    pub fn qmul_with(&self, b: &Self, rounding: Rounding) -> Self {
+        Self::from_raw(Self::rescale(&(&self.raw * &b.raw), rounding))
+    }
+
+    pub fn qdiv(&self, b: &Self) -> Self {
+        self.qdiv_with(b, Rounding::TowardZero)
+    }
+
+    pub fn qdiv_with(&self, b: &Self, rounding: Rounding) -> Self {
+        assert!(b.raw.sign() != Sign::NoSign, "division by zero");
+        // the chip rounds |a| S / |b| and applies the sign, which is the same as rounding
+        // sign(b) a S / |b| in every mode
+        let numerator = &self.raw << PRECISION;
+        let numerator = if b.is_neg() { -numerator } else { numerator };
+        let denominator = BigInt::from_biguint(Sign::Plus, b.raw.magnitude().clone());
+        Self::from_raw(round_div(&numerator, &denominator, rounding))
+    }
+
+    /// `a mod b` for `b > 0`. Like the chip, a negative multiple of `b` is mapped to `b`.
+    pub fn qmod(&self, b: &Self) -> Self {
+        assert!(b.raw.sign() == Sign::Plus, "modulus must be positive");
+        let res_abs = self.raw.magnitude() % b.raw.magnitude();
+        let res_abs = BigInt::from_biguint(Sign::Plus, res_abs);
+        if self.is_neg() {
+            Self::from_raw(&b.raw - res_abs)
+        } else {
+            Self::from_raw(res_abs)
+        }
+    }
+
+    /// Rescales once after summing the products.
+    pub fn inner_product(a: &[Self], b: &[Self]) -> Self {
+        Self::inner_product_with(a, b, Rounding::Floor)
+    }
+
+    pub fn inner_product_with(a: &[Self], b: &[Self], rounding: Rounding) -> Self {
+        assert_eq!(a.len(), b.len());
+        let sum: BigInt = a.iter().zip(b.iter()).map(|(a, b)| &a.raw * &b.raw).sum();
+        Self::from_raw(Self::rescale(&sum, rounding))
+    }
+
+    /// Same as `FixedPointInstructions::clip` without overflow checks: reduces `|x|` modulo
+    /// `max_value` and keeps the sign.
+    pub fn clip(&self) -> Self {
+        let res_abs =
+            BigInt::from_biguint(Sign::Plus, self.raw.magnitude() % Self::max_value().magnitude());
+        if self.is_neg() {
+            Self::from_raw(-res_abs)
+        } else {
+            Self::from_raw(res_abs)
+        }
+    }
+
+    pub fn qmax(&self, b: &Self) -> Self {
+        if self.raw < b.raw {
+            b.clone()
+        } else {
+            self.clone()
+        }
+    }
+
+    pub fn qmin(&self, b: &Self) -> Self {
+        if self.raw < b.raw {
+            self.clone()
+        } else {
+            b.clone()
+        }
+    }
+
+    pub fn qfloor(&self) -> Self {
+        Self::from_raw(self.to_integer() * Self::scale())
+    }
+
+    pub fn qceil(&self) -> Self {
+        self.neg().qfloor().neg()
+    }
+
+    /// Ties away from zero, like `f64::round` and `FixedPointInstructions::qround`.
+    pub fn qround(&self) -> Self {
+        let half = Self::from_raw(Self::scale() / 2u32);
+        if self.is_neg() {
+            self.neg().qadd(&half).qfloor().neg()
+        } else {
+            self.qadd(&half).qfloor()
+        }
+    }
+
+    pub fn qtrunc(&self) -> Self {
+        if self.is_neg() {
+            self.qceil()
+        } else {
+            self.qfloor()
+        }
+    }
+
+    /// `x - floor(x)` in `[0, 1)`.
+    pub fn qfrac(&self) -> Self {
+        self.qsub(&self.qfloor())
+    }
+
+    /// `floor(x)` as an integer, like `FixedPointInstructions::to_integer`.
+    pub fn to_integer(&self) -> BigInt {
+        self.raw.div_floor(&Self::scale())
+    }
+
+    /// Like `FixedPointInstructions::from_integer`, `|n|` must be below `2^PRECISION`.
+    pub fn from_integer(n: &BigInt) -> Self {
+        assert!(n.magnitude().bits() <= PRECISION as u64, "integer is out of range");
+        Self::from_raw(n * Self::scale())
+    }
+
+    /// Horner's method with the coefficient of the highest power first, rescaling after every
+    /// multiplication.
+    pub fn polynomial(&self, coef: &[Self]) -> Self {
+        let mut acc = Self::zero();
+        for (idx, c) in coef.iter().enumerate() {
+            let y_add = acc.qadd(c);
+            if idx + 1 == coef.len() {
+                return y_add;
+            }
+            acc = self.qmul(&y_add);
+        }
+
+        acc
+    }
+
+    fn quantize_poly(coef: &[f64]) -> Vec<Self> {
+        coef.iter().map(|c| Self::quantize(*c)).collect()
+    }
+
+    pub fn qexp2(&self) -> Self {
+        let a_abs = self.qabs();
+        let (int_part, frac_part) = a_abs.raw.div_mod_floor(&Self::scale());
+        let int_part: u32 = int_part.to_string().parse().expect("exponent is too large");
+        let y_frac = Self::from_raw(frac_part).polynomial(&Self::quantize_poly(&EXP2_POLY));
+        let res_pos = Self::from_raw(y_frac.raw << int_part);
+        if self.is_neg() {
+            Self::one().qdiv(&res_pos)
+        } else {
+            res_pos
+        }
+    }
+
+    pub fn qlog2(&self) -> Self {
+        assert!(self.raw.sign() == Sign::Plus, "log2 needs a positive input");
+        // a = 2^num_digits + ..., normalized to [2, 4)
+        let num_digits = self.raw.bits() as i64 - 1;
+        let shift = PRECISION as i64 + 1 - num_digits;
+        let a_norm = if shift < 0 {
+            Self::from_raw(self.raw.clone() >> (-shift) as u32)
+        } else {
+            Self::from_raw(self.raw.clone() << shift as u32)
+        };
+        let log_a_norm = a_norm.polynomial(&Self::quantize_poly(&LOG2_POLY));
+
+        Self::from_raw(log_a_norm.raw - BigInt::from(shift) * Self::scale())
+    }
+
+    pub fn qexp(&self) -> Self {
+        let ln2 = Self::quantize(2.0f64.ln());
+        self.qdiv(&ln2).qexp2()
+    }
+
+    pub fn qrecip(&self) -> Self {
+        Self::one().qdiv(self)
+    }
+
+    pub fn qlog(&self) -> Self {
+        let log2e = Self::quantize(std::f64::consts::LOG2_E);
+        self.qlog2().qdiv(&log2e)
+    }
+
+    pub fn qpow(&self, exponent: &Self) -> Self {
+        exponent.qmul(&self.qlog()).qexp()
+    }
+
+    /// Rounds toward negative infinity, like `FixedPointChip::qsqrt`.
+    pub fn qsqrt(&self) -> Self {
+        assert!(!self.is_neg(), "square root of a negative number");
+        let res = (self.raw.magnitude() << PRECISION).sqrt();
+        Self::from_raw(BigInt::from_biguint(Sign::Plus, res))
+    }
+
+    pub fn qsin(&self) -> Self {
+        let a_abs = self.qabs();
+        let a_mod = a_abs.qmod(&Self::quantize(std::f64::consts::PI * 2.0));
+        let a_mpi = a_mod.qsub(&Self::quantize(std::f64::consts::PI));
+        let coef = Self::quantize_poly(&SIN_POLY);
+        // sin(a) for a < pi and -sin(a - pi) for pi <= a < 2pi
+        let sin_a_abs =
+            if a_mpi.is_neg() { a_mod.polynomial(&coef) } else { a_mpi.polynomial(&coef).neg() };
+        if self.is_neg() {
+            sin_a_abs.neg()
+        } else {
+            sin_a_abs
+        }
+    }
+
+    pub fn qcos(&self) -> Self {
+        self.qadd(&Self::quantize(std::f64::consts::FRAC_PI_2)).qsin()
+    }
+
+    pub fn qtan(&self) -> Self {
+        self.qsin().qdiv(&self.qcos())
+    }
+
+    pub fn qatan(&self) -> Self {
+        Self::qatan2(self, &Self::one())
+    }
+
+    /// Same as `FixedPointInstructions::qatan2`: the angle of `(x, y)` in `(-pi, pi]`.
+    pub fn qatan2(y: &Self, x: &Self) -> Self {
+        assert!(!(x.raw.sign() == Sign::NoSign && y.raw.sign() == Sign::NoSign), "atan2(0, 0)");
+        let (x_abs, y_abs) = (x.qabs(), y.qabs());
+        let x_lt_y = x_abs < y_abs;
+        let ratio = if x_lt_y { x_abs.qdiv(&y_abs) } else { y_abs.qdiv(&x_abs) };
+        let atan_ratio =
+            ratio.qmul(&ratio.qmul(&ratio).polynomial(&Self::quantize_poly(&ATAN_POLY)));
+        let angle = if x_lt_y {
+            Self::quantize(std::f64::consts::FRAC_PI_2).qsub(&atan_ratio)
+        } else {
+            atan_ratio
+        };
+        let angle =
+            if x.is_neg() { Self::quantize(std::f64::consts::PI).qsub(&angle) } else { angle };
+        if y.is_neg() {
+            angle.neg()
+        } else {
+            angle
+        }
+    }
+
+    pub fn qasin(&self) -> Self {
+        Self::qatan2(self, &self.cos_of_asin())
+    }
+
+    pub fn qacos(&self) -> Self {
+        Self::qatan2(&self.cos_of_asin(), self)
+    }
+
+    // sqrt((1 - a)(1 + a)) of the unscaled product, like the chip
+    fn cos_of_asin(&self) -> Self {
+        let square = Self::one().qsub(self).raw * Self::one().qadd(self).raw;
+        assert!(square.sign() != Sign::Minus, "asin or acos of a value outside of [-1, 1]");
+        Self::from_raw(BigInt::from_biguint(Sign::Plus, square.magnitude().sqrt()))
+    }
+
+    pub fn qsigmoid(&self) -> Self {
+        let ln2 = Self::quantize(2.0f64.ln());
+        let z = self.qabs().neg().qdiv(&ln2);
+        let min_z = Self::quantize(1.0 - PRECISION as f64);
+        let denom = Self::one().qadd(&z.qmax(&min_z).qexp2());
+        let y_pos = Self::one().qdiv(&denom);
+        if self.is_neg() {
+            Self::one().qsub(&y_pos)
+        } else {
+            y_pos
+        }
+    }
+}
+
+/// `numerator / denominator` for a positive denominator, rounded with `rounding`.
+pub fn round_div(numerator: &BigInt, denominator: &BigInt, rounding: Rounding) -> BigInt {
+    assert!(denominator.sign() == Sign::Plus, "denominator must be positive");
+    let (q, r) = numerator.div_mod_floor(denominator);
+    let round_up = match rounding {
+        Rounding::Floor => false,
+        Rounding::HalfEven => {
+            let two_r = &r * 2u32;
+            two_r > *denominator || (two_r == *denominator && q.is_odd())
+        }
+        Rounding::TowardZero => numerator.sign() == Sign::Minus && r.sign() != Sign::NoSign,
+    };
+    if round_up {
+        q + 1u32
+    } else {
+        q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{round_div, FixedPoint};
+    use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions, Rounding};
+    use crate::gadget::testing::{
+        for_each_precision, mock_run_at, mock_run_with, mock_verify_at, mock_verify_with,
+    };
+    use halo2_base::gates::GateInstructions;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::{AssignedValue, Context};
+    use num_bigint::{BigInt, Sign};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const SAMPLES: usize = 8;
+
+    fn samples(seed: u64, lo: f64, hi: f64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..SAMPLES).map(|_| rng.gen_range(lo..hi)).collect()
+    }
+
+    // `x`, reduced at low precisions so that products of two samples, and their sum over the
+    // samples, stay in the valid range |x| < 2^P
+    fn factor_range<const P: u32>(x: f64) -> f64 {
+        x.min(2f64.powi(P as i32 / 2 - 2))
+    }
+
+    fn ulp<const P: u32>() -> f64 {
+        2f64.powi(-(P as i32))
+    }
+
+    // compares a unary chip function against its emulation on random inputs in [lo, hi)
+    fn check_unary<const P: u32>(
+        seed: u64,
+        lo: f64,
+        hi: f64,
+        chip_fn: impl Fn(&FixedPointChip<Fr>, &mut Context<Fr>, AssignedValue<Fr>) -> AssignedValue<Fr>,
+        native_fn: impl Fn(&FixedPoint<P>) -> FixedPoint<P>,
+    ) {
+        let inputs = samples(seed, lo, hi);
+        let outputs = mock_run_at(P, |chip, ctx| {
+            inputs
+                .iter()
+                .map(|x| {
+                    let x = ctx.load_witness(chip.quantization(*x));
+                    chip_fn(chip, ctx, x)
+                })
+                .collect()
+        });
+        for (x, output) in inputs.iter().zip(outputs) {
+            let expected = native_fn(&FixedPoint::quantize(*x));
+            assert_eq!(FixedPoint::from_field(output), expected, "input {x}, precision {P}");
+        }
+    }
+
+    // compares a binary chip function against its emulation on random inputs in [lo, hi)
+    fn check_binary<const P: u32>(
+        seed: u64,
+        lo: f64,
+        hi: f64,
+        chip_fn: impl Fn(
+            &FixedPointChip<Fr>,
+            &mut Context<Fr>,
+            AssignedValue<Fr>,
+            AssignedValue<Fr>,
+        ) -> AssignedValue<Fr>,
+        native_fn: impl Fn(&FixedPoint<P>, &FixedPoint<P>) -> FixedPoint<P>,
+    ) {
+        let a = samples(seed, lo, hi);
+        let b = samples(seed + 1, lo, hi);
+        let outputs = mock_run_at(P, |chip, ctx| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| {
+                    let a = ctx.load_witness(chip.quantization(*a));
+                    let b = ctx.load_witness(chip.quantization(*b));
+                    chip_fn(chip, ctx, a, b)
+                })
+                .collect()
+        });
+        for ((a, b), output) in a.iter().zip(b.iter()).zip(outputs) {
+            let expected = native_fn(&FixedPoint::quantize(*a), &FixedPoint::quantize(*b));
+            assert_eq!(FixedPoint::from_field(output), expected, "inputs {a}, {b}, precision {P}");
+        }
+    }
+
+    fn field_roundtrip<const P: u32>() {
+        let range = 1e6f64.min(2f64.powi(P as i32 - 1));
+        for x in samples(0, -range, range) {
+            let fixed = FixedPoint::<P>::quantize(x);
+            let chip_value =
+                mock_run_at(P, |chip, ctx| vec![ctx.load_witness(chip.quantization(x))]);
+            assert_eq!(fixed.to_field::<Fr>(), chip_value[0]);
+            assert_eq!(FixedPoint::from_field(chip_value[0]), fixed);
+            assert!((fixed.to_f64() - x).abs() < ulp::<P>() + 1e-9, "{x}, precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_field_roundtrip() {
+        for_each_precision!(field_roundtrip);
+    }
+
+    fn arithmetic<const P: u32>() {
+        let r = factor_range::<P>(1000.0);
+        check_binary::<P>(1, -r, r, |chip, ctx, a, b| chip.qadd(ctx, a, b), |a, b| a.qadd(b));
+        check_binary::<P>(3, -r, r, |chip, ctx, a, b| chip.qsub(ctx, a, b), |a, b| a.qsub(b));
+        check_binary::<P>(5, -r, r, |chip, ctx, a, b| chip.qmul(ctx, a, b), |a, b| a.qmul(b));
+        check_binary::<P>(7, -r, r, |chip, ctx, a, b| chip.qdiv(ctx, a, b), |a, b| a.qdiv(b));
+        check_binary::<P>(9, -r, r, |chip, ctx, a, b| chip.qmax(ctx, a, b), |a, b| a.qmax(b));
+        check_binary::<P>(11, -r, r, |chip, ctx, a, b| chip.qmin(ctx, a, b), |a, b| a.qmin(b));
+        check_unary::<P>(13, -r, r, |chip, ctx, a| chip.qabs(ctx, a), |a| a.qabs());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        for_each_precision!(arithmetic);
+    }
+
+    fn overflow_checks<const P: u32>() {
+        // (2^p - 2^{-p}) + (2^p - 2^{-p}) and its square are out of range and wrap around in
+        // unchecked mode, which the mock prover accepts; checked mode rejects them
+        let near_max = FixedPoint::<P>::from_raw(FixedPoint::<P>::max_value() - BigInt::from(1));
+        let small = FixedPoint::<P>::quantize(1.5);
+        for op in ["qadd", "qmul"] {
+            for (a, checked, accepted) in
+                [(&near_max, false, true), (&near_max, true, false), (&small, true, true)]
+            {
+                let configure = |chip: FixedPointChip<Fr>| {
+                    if checked {
+                        chip.with_overflow_checks()
+                    } else {
+                        chip
+                    }
+                };
+                let verified = mock_verify_with(P, configure, |chip, ctx| {
+                    let a = ctx.load_witness(a.to_field());
+                    match op {
+                        "qadd" => chip.qadd(ctx, a, a),
+                        _ => chip.qmul(ctx, a, a),
+                    };
+                });
+                assert_eq!(verified, accepted, "{op} on {a:?}, checked: {checked}, precision {P}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow_checks() {
+        for_each_precision!(overflow_checks);
+    }
+
+    fn is_neg<const P: u32>() {
+        // is_neg accepts unscaled products, |a| < 2^{4p}, and rejects values beyond that
+        let max_value = FixedPoint::<P>::max_value();
+        let product_bound = &max_value * &max_value;
+        let one = BigInt::from(1);
+        let inputs = [
+            BigInt::from(0),
+            one.clone(),
+            -one.clone(),
+            max_value.clone(),
+            -max_value.clone(),
+            &product_bound - &one,
+            -(&product_bound - &one),
+        ];
+        let outputs = mock_run_at(P, |chip, ctx| {
+            inputs
+                .iter()
+                .map(|a| {
+                    let a = ctx.load_witness(FixedPoint::<P>::from_raw(a.clone()).to_field());
+                    chip.is_neg(ctx, a)
+                })
+                .collect()
+        });
+        for (a, output) in inputs.iter().zip(outputs) {
+            let expected = Fr::from((a.sign() == Sign::Minus) as u64);
+            assert_eq!(output, expected, "is_neg({a}), precision {P}");
+        }
+        for a in [&product_bound * 2, -(&product_bound + &one)] {
+            let verified = mock_verify_at(P, |chip, ctx| {
+                let a = ctx.load_witness(FixedPoint::<P>::from_raw(a.clone()).to_field());
+                chip.is_neg(ctx, a);
+            });
+            assert!(!verified, "is_neg({a}), precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_is_neg() {
+        for_each_precision!(is_neg);
+    }
+
+    fn inner_product<const P: u32>() {
+        let r = factor_range::<P>(100.0);
+        let a = samples(20, -r, r);
+        let b = samples(21, -r, r);
+        let output = mock_run_at(P, |chip, ctx| {
+            let a = chip.load_vector(ctx, &a);
+            let b = chip.load_vector(ctx, &b);
+            vec![chip.inner_product(ctx, a, b)]
+        });
+        let a: Vec<FixedPoint<P>> = a.iter().map(|x| FixedPoint::quantize(*x)).collect();
+        let b: Vec<FixedPoint<P>> = b.iter().map(|x| FixedPoint::quantize(*x)).collect();
+        assert_eq!(FixedPoint::from_field(output[0]), FixedPoint::inner_product(&a, &b));
+    }
+
+    #[test]
+    fn test_inner_product() {
+        for_each_precision!(inner_product);
+    }
+
+    fn exp_log<const P: u32>() {
+        check_unary::<P>(30, -10.0, 10.0, |chip, ctx, a| chip.qexp2(ctx, a), |a| a.qexp2());
+        check_unary::<P>(31, 0.01, 1000.0, |chip, ctx, a| chip.qlog2(ctx, a), |a| a.qlog2());
+        check_unary::<P>(32, -10.0, 10.0, |chip, ctx, a| chip.qexp(ctx, a), |a| a.qexp());
+        check_unary::<P>(33, 0.01, 1000.0, |chip, ctx, a| chip.qlog(ctx, a), |a| a.qlog());
+        check_unary::<P>(35, -10.0, 10.0, |chip, ctx, a| chip.qsigmoid(ctx, a), |a| a.qsigmoid());
+        // e^{-|x|} is clamped to 2^{1 - P} far beyond (P - 1) ln(2), up to half the valid range
+        let far = 1e6f64.min(2f64.powi(P as i32 - 1));
+        let inputs = [-60.0, 60.0, -far, far];
+        let outputs = mock_run_at(P, |chip, ctx| {
+            inputs
+                .iter()
+                .map(|x| {
+                    let x = ctx.load_witness(chip.quantization(*x));
+                    chip.qsigmoid(ctx, x)
+                })
+                .collect()
+        });
+        for (x, output) in inputs.iter().zip(outputs) {
+            let expected = FixedPoint::<P>::quantize(*x).qsigmoid();
+            assert_eq!(FixedPoint::from_field(output), expected, "input {x}, precision {P}");
+            let err = expected.to_f64() - if *x < 0.0 { 0.0 } else { 1.0 };
+            assert!(err.abs() <= 2.0 * ulp::<P>(), "sigmoid({x}) error {err}, precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_exp_log() {
+        for_each_precision!(exp_log);
+    }
+
+    fn sqrt_recip<const P: u32>() {
+        check_unary::<P>(34, 0.0, 1000.0, |chip, ctx, a| chip.qsqrt(ctx, a), |a| a.qsqrt());
+        check_unary::<P>(36, -1000.0, 1000.0, |chip, ctx, a| chip.qrecip(ctx, a), |a| a.qrecip());
+        // against f64 on the quantized inputs: qsqrt is below sqrt by less than one unit in the
+        // last place, qrecip and qdiv are off by less than one unit in either direction; `eps`
+        // covers the rounding of f64, which dominates at high precisions
+        let ulp = ulp::<P>();
+        let eps = 1e-12;
+        for x in samples(37, 0.0, 1000.0) {
+            let x = FixedPoint::<P>::quantize(x);
+            let err = x.qsqrt().to_f64() - x.to_f64().sqrt();
+            assert!(-ulp - eps < err && err <= eps, "sqrt({}) error {err}", x.to_f64());
+        }
+        for x in samples(38, -1000.0, 1000.0) {
+            let x = FixedPoint::<P>::quantize(x);
+            let err = x.qrecip().to_f64() - 1.0 / x.to_f64();
+            assert!(err.abs() < ulp + eps, "1 / {} error {err}", x.to_f64());
+        }
+        for (a, b) in samples(39, -1000.0, 1000.0).into_iter().zip(samples(40, 1.0, 1000.0)) {
+            let (a, b) = (FixedPoint::<P>::quantize(a), FixedPoint::<P>::quantize(b));
+            let err = a.qdiv(&b).to_f64() - a.to_f64() / b.to_f64();
+            assert!(err.abs() < ulp + eps, "{} / {} error {err}", a.to_f64(), b.to_f64());
+        }
+    }
+
+    #[test]
+    fn test_sqrt_recip() {
+        for_each_precision!(sqrt_recip);
+    }
+
+    #[test]
+    fn test_round_div() {
+        // -1.75, -1.5, -0.5, 0.5, 1.5, 1.75, 2.5
+        let numerators = [-7, -6, -2, 2, 6, 7, 10];
+        let expected = [
+            (Rounding::Floor, [-2, -2, -1, 0, 1, 1, 2]),
+            (Rounding::HalfEven, [-2, -2, 0, 0, 2, 2, 2]),
+            (Rounding::TowardZero, [-1, -1, 0, 0, 1, 1, 2]),
+        ];
+        for (rounding, expected) in expected {
+            for (n, q) in numerators.iter().zip(expected) {
+                let res = round_div(&BigInt::from(*n), &BigInt::from(4), rounding);
+                assert_eq!(res, BigInt::from(q), "{n} / 4 with {rounding:?}");
+            }
+        }
+    }
+
+    fn rounding<const P: u32>() {
+        let modes = [(60, Rounding::Floor), (62, Rounding::HalfEven), (64, Rounding::TowardZero)];
+        let r = factor_range::<P>(1000.0);
+        for (seed, rounding) in modes {
+            let mut a: Vec<FixedPoint<P>> =
+                samples(seed, -r, r).into_iter().map(FixedPoint::quantize).collect();
+            let mut b: Vec<FixedPoint<P>> =
+                samples(seed + 1, -r, r).into_iter().map(FixedPoint::quantize).collect();
+            // exact ties: (+-1, +-3) * 2^{-p} times 0.5 and divided by 2
+            for raw in [-3, -1, 1, 3] {
+                a.push(FixedPoint::from_raw(BigInt::from(raw)));
+                b.push(FixedPoint::quantize(0.5));
+                a.push(FixedPoint::from_raw(BigInt::from(raw)));
+                b.push(FixedPoint::quantize(2.0));
+            }
+            let outputs = mock_run_with(
+                P,
+                |chip| chip.with_rounding(rounding),
+                |chip, ctx| {
+                    let a: Vec<_> = a.iter().map(|x| ctx.load_witness(x.to_field())).collect();
+                    let b: Vec<_> = b.iter().map(|x| ctx.load_witness(x.to_field())).collect();
+                    let mut outputs = vec![];
+                    for (a, b) in a.iter().zip(b.iter()) {
+                        outputs.push(chip.qmul(ctx, *a, *b));
+                        outputs.push(chip.qdiv(ctx, *a, *b));
+                    }
+                    outputs.push(chip.inner_product(ctx, a, b));
+                    outputs
+                },
+            );
+            let mut expected = vec![];
+            for (a, b) in a.iter().zip(b.iter()) {
+                expected.push(a.qmul_with(b, rounding));
+                expected.push(a.qdiv_with(b, rounding));
+            }
+            expected.push(FixedPoint::inner_product_with(&a, &b, rounding));
+            let outputs: Vec<FixedPoint<P>> =
+                outputs.into_iter().map(FixedPoint::from_field).collect();
+            assert_eq!(outputs, expected, "{rounding:?}, precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        for_each_precision!(rounding);
+    }
+
+    fn comparisons<const P: u32>() {
+        // negative, equal and near +-max_value operands
+        let max = FixedPoint::<P>::max_value();
+        let values: Vec<FixedPoint<P>> = [
+            BigInt::from(1) - &max,
+            BigInt::from(2) - &max,
+            FixedPoint::<P>::quantize(-2.5).raw().clone(),
+            BigInt::from(-1),
+            BigInt::from(0),
+            FixedPoint::<P>::quantize(2.5).raw().clone(),
+            &max - BigInt::from(2),
+            &max - BigInt::from(1),
+        ]
+        .into_iter()
+        .map(FixedPoint::from_raw)
+        .collect();
+        let pairs: Vec<(&FixedPoint<P>, &FixedPoint<P>)> =
+            values.iter().flat_map(|a| values.iter().map(move |b| (a, b))).collect();
+        let tol = FixedPoint::<P>::quantize(5.0);
+        let (lo, hi) = (&values[2], &values[6]);
+        let outputs = mock_run_at(P, |chip, ctx| {
+            let tol = ctx.load_witness(tol.to_field());
+            let neg_tol = ctx.load_witness(-*tol.value());
+            let (lo, hi) = (ctx.load_witness(lo.to_field()), ctx.load_witness(hi.to_field()));
+            let mut outputs = vec![];
+            for (a, b) in pairs.iter() {
+                let a = ctx.load_witness(a.to_field());
+                let b = ctx.load_witness(b.to_field());
+                outputs.push(chip.qlt(ctx, a, b));
+                outputs.push(chip.qle(ctx, a, b));
+                outputs.push(chip.qgt(ctx, a, b));
+                outputs.push(chip.qge(ctx, a, b));
+                outputs.push(chip.qeq_within(ctx, a, b, tol));
+                outputs.push(chip.qeq_within(ctx, a, b, neg_tol));
+                outputs.push(chip.qclamp(ctx, a, lo, hi));
+            }
+            outputs
+        });
+        for ((a, b), outputs) in pairs.iter().zip(outputs.chunks(7)) {
+            let within = a.qsub(b).qabs() <= tol;
+            let expected = [a < b, a <= b, a > b, a >= b, within, false];
+            for (output, expected) in outputs.iter().zip(expected) {
+                assert_eq!(*output, Fr::from(expected as u64), "{a:?}, {b:?}, precision {P}");
+            }
+            let clamped = a.qmax(lo).qmin(hi);
+            assert_eq!(FixedPoint::from_field(outputs[6]), clamped, "qclamp({a:?})");
+        }
+
+        // a false claim that |1 - 1.5| <= 0.25 is rejected
+        for (b, accepted) in [(1.25, true), (1.5, false)] {
+            let verified = mock_verify_at(P, |chip, ctx| {
+                let a = ctx.load_witness(chip.quantization(1.0));
+                let b = ctx.load_witness(chip.quantization(b));
+                let tol = ctx.load_witness(chip.quantization(0.25));
+                let within = chip.qeq_within(ctx, a, b, tol);
+                chip.gate().assert_is_const(ctx, &within, &Fr::from(1));
+            });
+            assert_eq!(verified, accepted, "|1 - {b}| <= 0.25, precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        for_each_precision!(comparisons);
+    }
+
+    fn integer_parts<const P: u32>() {
+        check_unary::<P>(50, -100.0, 100.0, |chip, ctx, a| chip.qfloor(ctx, a), |a| a.qfloor());
+        check_unary::<P>(51, -100.0, 100.0, |chip, ctx, a| chip.qceil(ctx, a), |a| a.qceil());
+        check_unary::<P>(52, -100.0, 100.0, |chip, ctx, a| chip.qround(ctx, a), |a| a.qround());
+        check_unary::<P>(53, -100.0, 100.0, |chip, ctx, a| chip.qtrunc(ctx, a), |a| a.qtrunc());
+        check_unary::<P>(54, -100.0, 100.0, |chip, ctx, a| chip.qfrac(ctx, a), |a| a.qfrac());
+
+        // integers and halves, where the rounding direction matters, against f64
+        let inputs = [-2.5, -2.0, -1.5, -0.75, -0.5, 0.0, 0.5, 0.75, 1.5, 2.0, 2.5];
+        let outputs = mock_run_at(P, |chip, ctx| {
+            inputs
+                .iter()
+                .flat_map(|x| {
+                    let x = ctx.load_witness(chip.quantization(*x));
+                    let n = chip.to_integer(ctx, x);
+                    [
+                        chip.qfloor(ctx, x),
+                        chip.qceil(ctx, x),
+                        chip.qround(ctx, x),
+                        chip.qtrunc(ctx, x),
+                        chip.qfrac(ctx, x),
+                        chip.from_integer(ctx, n),
+                        n,
+                    ]
+                })
+                .collect()
+        });
+        for (x, outputs) in inputs.iter().zip(outputs.chunks(7)) {
+            let expected = [x.floor(), x.ceil(), x.round(), x.trunc(), x - x.floor(), x.floor()];
+            for (output, expected) in outputs.iter().zip(expected) {
+                let expected = FixedPoint::<P>::quantize(expected);
+                assert_eq!(FixedPoint::from_field(*output), expected, "input {x}, precision {P}");
+            }
+            let n = FixedPoint::<P>::from_raw(BigInt::from(x.floor() as i64));
+            assert_eq!(outputs[6], n.to_field::<Fr>(), "to_integer({x})");
+            assert_eq!(FixedPoint::<P>::quantize(*x).to_integer(), *n.raw(), "to_integer({x})");
+        }
+
+        // |n| < 2^p is constrained also without overflow checks
+        let limit = FixedPoint::<P>::scale();
+        let one = BigInt::from(1);
+        for (n, valid) in
+            [(&limit - &one, true), (&one - &limit, true), (limit.clone(), false), (-&limit, false)]
+        {
+            let accepted = mock_verify_at(P, |chip, ctx| {
+                let n = ctx.load_witness(FixedPoint::<P>::from_raw(n.clone()).to_field());
+                chip.from_integer(ctx, n);
+            });
+            assert_eq!(accepted, valid, "from_integer({n}), precision {P}");
+        }
+    }
+
+    #[test]
+    fn test_integer_parts() {
+        for_each_precision!(integer_parts);
+    }
+
+    fn trigonometry<const P: u32>() {
+        check_unary::<P>(40, -10.0, 10.0, |chip, ctx, a| chip.qsin(ctx, a), |a| a.qsin());
+        check_unary::<P>(41, -10.0, 10.0, |chip, ctx, a| chip.qcos(ctx, a), |a| a.qcos());
+    }
+
+    #[test]
+    fn test_trigonometry() {
+        for_each_precision!(trigonometry);
+    }
+
+    fn inverse_trigonometry<const P: u32>() {
+        check_unary::<P>(42, -10.0, 10.0, |chip, ctx, a| chip.qatan(ctx, a), |a| a.qatan());
+        check_binary::<P>(
+            43,
+            -10.0,
+            10.0,
+            |chip, ctx, y, x| chip.qatan2(ctx, y, x),
+            |y, x| FixedPoint::qatan2(y, x),
+        );
+        check_unary::<P>(45, -1.0, 1.0, |chip, ctx, a| chip.qasin(ctx, a), |a| a.qasin());
+        check_unary::<P>(46, -1.0, 1.0, |chip, ctx, a| chip.qacos(ctx, a), |a| a.qacos());
+
+        // the axes and diagonals, where the quadrant handling matters most
+        let points = [(0.0, 1.0), (1.0, 0.0), (0.0, -1.0), (-1.0, 0.0), (1.0, -1.0), (-1.0, -1.0)];
+        let outputs = mock_run_at(P, |chip, ctx| {
+            points
+                .iter()
+                .map(|(y, x)| {
+                    let y = ctx.load_witness(chip.quantization(*y));
+                    let x = ctx.load_witness(chip.quantization(*x));
+                    chip.qatan2(ctx, y, x)
+                })
+                .collect()
+        });
+        for ((y, x), output) in points.iter().zip(outputs) {
+            let expected =
+                FixedPoint::qatan2(&FixedPoint::<P>::quantize(*y), &FixedPoint::quantize(*x));
+            assert_eq!(FixedPoint::from_field(output), expected, "atan2({y}, {x}), precision {P}");
+        }
+
+        // against f64 on the quantized inputs, including |a| = 1 for asin and acos; 1e-12 covers
+        // the rounding of f64 and the approximation error of ATAN_POLY at high precisions
+        let tolerance = 16.0 * ulp::<P>() + 1e-12;
+        let random_points = samples(47, -10.0, 10.0).into_iter().zip(samples(48, -10.0, 10.0));
+        for (y, x) in points.into_iter().chain(random_points) {
+            let (y, x) = (FixedPoint::<P>::quantize(y), FixedPoint::<P>::quantize(x));
+            let err = FixedPoint::qatan2(&y, &x).to_f64() - y.to_f64().atan2(x.to_f64());
+            assert!(err.abs() < tolerance, "atan2({}, {}) error {err}", y.to_f64(), x.to_f64());
+        }
+        for a in [-1.0, 0.0, 1.0].into_iter().chain(samples(49, -1.0, 1.0)) {
+            let a = FixedPoint::<P>::quantize(a);
+            let err = a.qasin().to_f64() - a.to_f64().asin();
+            assert!(err.abs() < tolerance, "asin({}) error {err}", a.to_f64());
+            let err = a.qacos().to_f64() - a.to_f64().acos();
+            assert!(err.abs() < tolerance, "acos({}) error {err}", a.to_f64());
+        }
+    }
+    #[test]
+    fn test_inverse_trigonometry() {
+        for_each_precision!(inverse_trigonometry);
+    }
+}
@@ -0,0 +1,133 @@
+//! Goodness-of-fit statistics of a linear model, computed in-circuit.
+//!
+//! Given the design matrix `x` (column-wise, including the intercept column if the model has
+//! one), the observations `y` and the coefficients `beta`:
+//! - RSS = sum (y - X beta)^2, TSS = sum (y - mean(y))^2
+//! - R^2 = 1 - RSS / TSS, adjusted R^2 = 1 - (1 - R^2) (n - 1) / (n - k)
+//! - RMSE = sqrt(RSS / n)
+//!
+//! where `n` is the number of observations and `k` the number of columns of `x`.
+use super::linear_predictions;
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use crate::gadget::stats;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{
+    utils::{BigPrimeField, ScalarField},
+    AssignedValue, Context,
+};
+
+/// Names of the statistics in the order of [`GoodnessOfFit::to_vec`].
+pub const LABELS: [&str; 5] = ["rss", "tss", "r_squared", "adjusted_r_squared", "rmse"];
+
+#[derive(Clone, Copy, Debug)]
+pub struct GoodnessOfFit<F: ScalarField> {
+    pub rss: AssignedValue<F>,
+    pub tss: AssignedValue<F>,
+    pub r_squared: AssignedValue<F>,
+    pub adjusted_r_squared: AssignedValue<F>,
+    pub rmse: AssignedValue<F>,
+}
+
+impl<F: ScalarField> GoodnessOfFit<F> {
+    /// Returns the statistics in the order of [`LABELS`], e.g. to make them public.
+    pub fn to_vec(&self) -> Vec<AssignedValue<F>> {
+        vec![self.rss, self.tss, self.r_squared, self.adjusted_r_squared, self.rmse]
+    }
+}
+
+/// Computes the residual sum of squares `sum (y - X beta)^2`.
+pub fn residual_sum_of_squares<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let predictions = linear_predictions(chip, ctx, x, beta);
+    assert_eq!(predictions.len(), y.len(), "need one observation per row of the design matrix");
+    let residuals: Vec<AssignedValue<F>> = y
+        .iter()
+        .zip(predictions.iter())
+        .map(|(y, prediction)| chip.qsub(ctx, *y, *prediction))
+        .collect();
+
+    chip.inner_product(ctx, residuals.iter().copied(), residuals.iter().copied())
+}
+
+/// Computes the goodness-of-fit statistics of the linear model `y ~ X beta`.
+/// Requires `n > k` and a non-constant `y`, since R^2 divides by the TSS. RSS and TSS are
+/// divided with `qdiv` and must be in the valid range.
+pub fn goodness_of_fit<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+) -> GoodnessOfFit<F> {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    let n = y.len();
+    let k = x.len();
+    assert!(n > k, "need more observations than coefficients");
+    let one = Constant(chip.quantization(1.0));
+    let n_q = Constant(chip.quantization(n as f64));
+
+    let rss = residual_sum_of_squares(chip, ctx, x, y, beta);
+
+    // total sum of squares
+    let deviations = stats::deviations(chip, ctx, y);
+    let tss = chip.inner_product(ctx, deviations.iter().copied(), deviations.iter().copied());
+
+    let rss_div_tss = chip.qdiv(ctx, rss, tss);
+    let r_squared = chip.qsub(ctx, one, rss_div_tss);
+
+    // (n - 1) / (n - k) only depends on the shape of the circuit
+    let dof_ratio = Constant(chip.quantization((n - 1) as f64 / (n - k) as f64));
+    let unexplained = chip.qmul(ctx, rss_div_tss, dof_ratio);
+    let adjusted_r_squared = chip.qsub(ctx, one, unexplained);
+
+    let mse = chip.qdiv(ctx, rss, n_q);
+    let rmse = chip.qsqrt(ctx, mse);
+
+    GoodnessOfFit { rss, tss, r_squared, adjusted_r_squared, rmse }
+}
+
+#[cfg(test)]
+mod test {
+    use super::goodness_of_fit;
+    use crate::gadget::fixed_point::FixedPointChip;
+    use crate::gadget::regression::assert_within_tolerance;
+    use crate::gadget::testing::{load_line, mock_run, mock_verify, PRECISION};
+
+    // the least squares fit y = 2.15 + 2.94 x has RSS = 0.082 and TSS = 43.3
+    const BETA: [f64; 2] = [2.15, 2.94];
+
+    #[test]
+    fn test_goodness_of_fit() {
+        let outputs = mock_run(|chip, ctx| {
+            let (x, y) = load_line(chip, ctx);
+            let beta = chip.load_vector(ctx, &BETA);
+            goodness_of_fit(chip, ctx, &x, &y, &beta).to_vec()
+        });
+        // rss, tss, r_squared, adjusted_r_squared, rmse
+        let expected = [0.082, 43.3, 0.9981062355658199, 0.9971593533487297, 0.14317821063276354];
+        for (output, expected) in outputs.iter().zip(expected) {
+            let output = FixedPointChip::dequantize(*output, PRECISION);
+            assert!((output - expected).abs() < 1e-6, "{output} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_claimed_r_squared() {
+        for (claimed, accepted) in [(0.9981062, true), (0.999, false)] {
+            let verified = mock_verify(|chip, ctx| {
+                let (x, y) = load_line(chip, ctx);
+                let beta = chip.load_vector(ctx, &BETA);
+                let fit = goodness_of_fit(chip, ctx, &x, &y, &beta);
+                let claimed = ctx.load_witness(chip.quantization(claimed));
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                assert_within_tolerance(chip, ctx, fit.r_squared, claimed, tolerance);
+            });
+            assert_eq!(verified, accepted, "claimed R^2 {claimed}");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Predictions from a private, committed linear model.
+//!
+//! The model owner keeps the coefficients private and publishes a Poseidon commitment to them.
+//! Predictions `y = x^T beta` on public query rows can then be proven against the commitment.
+//! The commitment includes a private salt, so low-entropy coefficients cannot be recovered by
+//! brute-forcing the hash.
+use super::{linear_predictions, ols::with_intercept};
+use crate::gadget::commitment::poseidon_commit;
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::{
+    utils::{biguint_to_fe, modulus, BigPrimeField},
+    AssignedValue, Context,
+};
+use num_bigint::BigUint;
+
+/// Returns the Poseidon commitment to `[salt, beta[0], ..., beta[k-1]]`.
+pub fn commit_model<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    beta: &[AssignedValue<F>],
+    salt: AssignedValue<F>,
+) -> AssignedValue<F> {
+    let values: Vec<AssignedValue<F>> = [salt].into_iter().chain(beta.iter().copied()).collect();
+    poseidon_commit(ctx, chip.gate(), &values)
+}
+
+/// Parses the salt of the model commitment, a decimal integer below the field modulus.
+pub fn parse_salt<F: BigPrimeField>(salt: &str) -> Result<F, String> {
+    let value = BigUint::parse_bytes(salt.as_bytes(), 10)
+        .ok_or_else(|| format!("salt {salt:?} is not a decimal integer"))?;
+    if value >= modulus::<F>() {
+        return Err(format!("salt {salt} is not below the field modulus"));
+    }
+
+    Ok(biguint_to_fe(&value))
+}
+
+/// Proves predictions of the private model `beta` on the query rows `x` (column-wise) and makes
+/// public, in this order: the model commitment, the query values column by column and the
+/// predictions. If `intercept` is set, `beta[0]` is the intercept and `x` has one column less
+/// than `beta`. Returns the predictions.
+pub fn prove_predictions<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    beta: &[AssignedValue<F>],
+    salt: AssignedValue<F>,
+    intercept: bool,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Vec<AssignedValue<F>> {
+    let commitment = commit_model(chip, ctx, beta, salt);
+    let design_matrix = if intercept { with_intercept(chip, ctx, x) } else { x.to_vec() };
+    let predictions = linear_predictions(chip, ctx, &design_matrix, beta);
+    make_public.push(commitment);
+    make_public.extend(x.iter().flatten().copied());
+    make_public.extend(predictions.iter().copied());
+
+    predictions
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_salt, prove_predictions};
+    use crate::gadget::commitment::{RATE, R_F, R_P, T};
+    use crate::gadget::native::FixedPoint;
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::modulus;
+    use snark_verifier_sdk::snark_verifier::{loader::native::NativeLoader, util::hash::Poseidon};
+
+    type Fixed = FixedPoint<PRECISION>;
+
+    const QUERY: [f64; 3] = [0.5, 1.0, 4.0];
+    const BETA: [f64; 2] = [2.15, 2.94];
+    const SALT: u64 = 12345;
+
+    /// Native Poseidon hash of `[salt, beta...]`, computed outside of the circuit.
+    fn native_commitment(salt: u64) -> Fr {
+        let mut poseidon = Poseidon::<Fr, Fr, T, RATE>::new::<R_F, R_P, 0>(&NativeLoader);
+        let values: Vec<Fr> = [Fr::from(salt)]
+            .into_iter()
+            .chain(BETA.iter().map(|b| Fixed::quantize(*b).to_field()))
+            .collect();
+        poseidon.update(&values);
+        poseidon.squeeze()
+    }
+
+    fn native_predictions() -> Vec<Fr> {
+        let beta: Vec<Fixed> = BETA.iter().map(|b| Fixed::quantize(*b)).collect();
+        QUERY
+            .iter()
+            .map(|x| Fixed::inner_product(&[Fixed::one(), Fixed::quantize(*x)], &beta).to_field())
+            .collect()
+    }
+
+    #[test]
+    fn test_prove_predictions() {
+        let public = mock_run(|chip, ctx| {
+            let x = chip.load_matrix(ctx, &[QUERY.to_vec()]);
+            let beta = chip.load_vector(ctx, &BETA);
+            let salt = ctx.load_witness(Fr::from(SALT));
+            let mut make_public = vec![];
+            prove_predictions(chip, ctx, &x, &beta, salt, true, &mut make_public);
+            make_public
+        });
+
+        let query: Vec<Fr> = QUERY.iter().map(|x| Fixed::quantize(*x).to_field()).collect();
+        assert_eq!(public.len(), 1 + 2 * QUERY.len());
+        assert_eq!(public[0], native_commitment(SALT));
+        assert_eq!(public[1..=QUERY.len()], query[..]);
+        assert_eq!(public[QUERY.len() + 1..], native_predictions()[..]);
+    }
+
+    #[test]
+    fn test_prove_predictions_mismatch() {
+        // Pins the public values to the expected ones, as a verifier does with the instances.
+        let prove = |salt: u64, predictions: Vec<Fr>| {
+            mock_verify(|chip, ctx| {
+                let x = chip.load_matrix(ctx, &[QUERY.to_vec()]);
+                let beta = chip.load_vector(ctx, &BETA);
+                let salt = ctx.load_witness(Fr::from(salt));
+                let mut make_public = vec![];
+                prove_predictions(chip, ctx, &x, &beta, salt, true, &mut make_public);
+                let expected = [native_commitment(SALT)].into_iter().chain(predictions);
+                let checked =
+                    [make_public[0]].into_iter().chain(make_public[QUERY.len() + 1..].to_vec());
+                for (cell, value) in checked.zip(expected) {
+                    chip.gate().assert_is_const(ctx, &cell, &value);
+                }
+            })
+        };
+
+        assert!(prove(SALT, native_predictions()));
+        assert!(!prove(SALT + 1, native_predictions()));
+        let mut wrong = native_predictions();
+        wrong[1] += Fr::from(1);
+        assert!(!prove(SALT, wrong));
+    }
+
+    #[test]
+    fn test_parse_salt() {
+        assert_eq!(parse_salt::<Fr>("12345"), Ok(Fr::from(12345)));
+        let below_modulus = (modulus::<Fr>() - 1u32).to_string();
+        assert_eq!(parse_salt::<Fr>(&below_modulus), Ok(-Fr::from(1)));
+        for salt in ["", "abc", "-1", "0x10", &modulus::<Fr>().to_string()] {
+            assert!(parse_salt::<Fr>(salt).is_err(), "salt {salt:?}");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Lasso (L1-regularized) regression, verified through the KKT conditions.
+//!
+//! `beta` minimizes `1/2 ||y - X beta||^2 + lambda ||beta||_1` iff, with `g = X^T (y - X beta)`,
+//! - `g[j] = lambda * sign(beta[j])` for every non-zero coefficient, and
+//! - `|g[j]| <= lambda` for every zero coefficient.
+//!
+//! Both conditions are checked up to a tolerance, so sparse claimed coefficients can be proven
+//! optimal without solving the (non-smooth) problem in-circuit.
+use super::assert_within_tolerance;
+use super::ols::{residual_gradient, DataBounds};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+/// Constrains the subgradient condition for a single penalized coefficient,
+/// where `gradient = X[j]^T (y - X beta)`.
+pub fn check_kkt_condition<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    gradient: AssignedValue<F>,
+    beta: AssignedValue<F>,
+    lambda: AssignedValue<F>,
+    tolerance: AssignedValue<F>,
+) {
+    let beta_is_zero = chip.gate().is_zero(ctx, beta);
+    // sign is +-1 (not quantized), so lambda * sign stays at the quantization scale
+    let beta_sign = chip.sign(ctx, beta);
+    let target = chip.gate().mul(ctx, lambda, beta_sign);
+    // beta != 0: tolerance - |g - lambda * sign(beta)| >= 0
+    let diff = chip.qsub(ctx, gradient, target);
+    let diff_abs = chip.qabs(ctx, diff);
+    let slack_active = chip.qsub(ctx, tolerance, diff_abs);
+    // beta == 0: lambda + tolerance - |g| >= 0
+    let gradient_abs = chip.qabs(ctx, gradient);
+    let bound = chip.qadd(ctx, lambda, tolerance);
+    let slack_zero = chip.qsub(ctx, bound, gradient_abs);
+
+    let slack = chip.gate().select(ctx, slack_zero, slack_active, beta_is_zero);
+    let slack_is_non_neg = chip.qge(ctx, slack, Constant(F::from(0)));
+    chip.gate().assert_is_const(ctx, &slack_is_non_neg, &F::from(1));
+}
+
+/// Constrains `beta` to be a Lasso optimum for `(x, y)` with penalty `lambda`, up to `tolerance`.
+/// If `intercept` is set, the first column of `x` is the intercept column
+/// (see [`super::ols::with_intercept`]) and its coefficient is not penalized,
+/// i.e. it only has to satisfy `|g[0]| <= tolerance`. `bounds` are the bounds of the data, see
+/// [`DataBounds`].
+#[allow(clippy::too_many_arguments)]
+pub fn verify_kkt<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    lambda: AssignedValue<F>,
+    tolerance: AssignedValue<F>,
+    intercept: bool,
+    bounds: &DataBounds,
+) {
+    let gradient = residual_gradient(chip, ctx, x, y, beta, bounds);
+    for (j, (g, b)) in gradient.into_iter().zip(beta.iter()).enumerate() {
+        if intercept && j == 0 {
+            assert_within_tolerance(chip, ctx, g, Constant(F::from(0)), tolerance);
+        } else {
+            check_kkt_condition(chip, ctx, g, *b, lambda, tolerance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_kkt;
+    use crate::gadget::regression::ols::DataBounds;
+    use crate::gadget::testing::{load_line, mock_verify};
+
+    #[test]
+    fn test_kkt() {
+        // with an unpenalized intercept the slope is soft-thresholded: (S_xy - lambda) / S_xx
+        // with S_xy = 14.7 and S_xx = 5, and zero for lambda >= 14.7
+        let cases = [
+            (2.2, [3.25, 2.5], true),
+            // the least squares fit violates the condition of a non-zero coefficient
+            (2.2, [2.15, 2.94], false),
+            // a zero coefficient is optimal iff |g| = 14.7 <= lambda
+            (20.0, [9.5, 0.0], true),
+            (10.0, [9.5, 0.0], false),
+        ];
+        for (lambda, beta, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let (x, y) = load_line(chip, ctx);
+                let beta = chip.load_vector(ctx, &beta);
+                let lambda = ctx.load_witness(chip.quantization(lambda));
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                let bounds = DataBounds::uniform(1, 20.0).with_intercept();
+                verify_kkt(chip, ctx, &x, &y, &beta, lambda, tolerance, true, &bounds);
+            });
+            assert_eq!(verified, accepted, "lambda {lambda}, beta {beta:?}");
+        }
+    }
+}
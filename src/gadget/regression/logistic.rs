@@ -0,0 +1,103 @@
+//! Logistic regression: inference and training optimality.
+//!
+//! As in [`super::ols`], the design matrix is given column-wise. The model predicts
+//! `p = sigmoid(X beta)` and `beta` maximizes the log-likelihood iff it is a stationary point,
+//! i.e. `X^T (y - sigmoid(X beta)) = 0`, which is checked up to a tolerance.
+use super::{assert_within_tolerance, linear_predictions};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::halo2_proofs::halo2curves::ff::Field;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+/// Returns the predicted probabilities `sigmoid(x_t^T beta)` for every row `t` of `x`.
+pub fn predict<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    beta: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    linear_predictions(chip, ctx, x, beta)
+        .into_iter()
+        .map(|logit| chip.qsigmoid(ctx, logit))
+        .collect()
+}
+
+/// Constrains `beta` to be a stationary point of the log-likelihood of the labels `y`,
+/// i.e. `|X[j]^T (y - sigmoid(X beta))| <= tolerance` for every column `j`. Every label is
+/// constrained to be 0 or 1. Returns the predicted probabilities on the training rows.
+pub fn verify_stationarity<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+) -> Vec<AssignedValue<F>> {
+    let probabilities = predict(chip, ctx, x, beta);
+    assert_eq!(probabilities.len(), y.len(), "every column needs one value per observation");
+    let scale_inv = chip.quantization_scale.invert().unwrap();
+    for y in y.iter() {
+        let label = chip.gate().mul(ctx, *y, Constant(scale_inv));
+        chip.gate().assert_bit(ctx, label);
+    }
+    let residuals: Vec<AssignedValue<F>> =
+        y.iter().zip(probabilities.iter()).map(|(y, p)| chip.qsub(ctx, *y, *p)).collect();
+    for column in x.iter() {
+        let gradient = chip.inner_product(ctx, column.iter().copied(), residuals.iter().copied());
+        assert_within_tolerance(chip, ctx, gradient, Constant(F::from(0)), tolerance);
+    }
+
+    probabilities
+}
+
+#[cfg(test)]
+mod test {
+    use super::{predict, verify_stationarity};
+    use crate::gadget::native::FixedPoint;
+    use crate::gadget::regression::ols::with_intercept;
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+
+    type Fixed = FixedPoint<PRECISION>;
+
+    // not linearly separable, so the log-likelihood has a maximum at (-2.67338, 1.188169)
+    const X: [f64; 8] = [0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0];
+    const Y: [f64; 8] = [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0];
+    const BETA: [f64; 2] = [-2.67338, 1.188169];
+
+    #[test]
+    fn test_stationarity() {
+        let cases = [
+            (Y, BETA, true),
+            (Y, [-2.67338, 1.238169], false),
+            // (-2.136622, 0.81463) is stationary if the seventh label is 0.5, which is no label
+            ([0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5, 1.0], [-2.136622, 0.81463], false),
+        ];
+        for (labels, beta, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let x = chip.load_matrix(ctx, &[X.to_vec()]);
+                let x = with_intercept(chip, ctx, &x);
+                let y = chip.load_vector(ctx, &labels);
+                let beta = chip.load_vector(ctx, &beta);
+                let tolerance = ctx.load_witness(chip.quantization(1e-4));
+                verify_stationarity(chip, ctx, &x, &y, &beta, tolerance);
+            });
+            assert_eq!(verified, accepted, "labels {labels:?}, beta {beta:?}");
+        }
+    }
+
+    #[test]
+    fn test_predict() {
+        let outputs = mock_run(|chip, ctx| {
+            let x = chip.load_matrix(ctx, &[X.to_vec()]);
+            let x = with_intercept(chip, ctx, &x);
+            let beta = chip.load_vector(ctx, &BETA);
+            predict(chip, ctx, &x, &beta)
+        });
+        let beta: Vec<Fixed> = BETA.iter().map(|b| Fixed::quantize(*b)).collect();
+        for (x, output) in X.iter().zip(outputs) {
+            let logit = Fixed::inner_product(&[Fixed::one(), Fixed::quantize(*x)], &beta);
+            assert_eq!(Fixed::from_field(output), logit.qsigmoid(), "x = {x}");
+        }
+    }
+}
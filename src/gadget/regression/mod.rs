@@ -0,0 +1,149 @@
+//! Regression gadgets built on top of the `FixedPointChip`.
+//!
+//! A regression proof is only meaningful if the claimed model is checked inside the circuit,
+//! so the helpers here load the claimed coefficients as witnesses, constrain them against the
+//! values computed in-circuit and expose them (together with the tolerance) as public instances.
+use crate::gadget::commitment::poseidon_commit;
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context, QuantumCell};
+
+pub mod fit;
+pub mod inference;
+pub mod lasso;
+pub mod logistic;
+pub mod ols;
+pub mod polynomial;
+pub mod ridge;
+pub mod significance;
+pub mod wls;
+
+/// Default bound on the magnitude of the regression data, see [`ols::DataBounds`].
+pub const DEFAULT_MAX_ABS: f64 = (1u64 << 20) as f64;
+
+/// [`DEFAULT_MAX_ABS`], for `#[serde(default = ...)]` in the circuit inputs.
+pub fn default_max_abs() -> f64 {
+    DEFAULT_MAX_ABS
+}
+
+/// Loads the claimed coefficients as (private) witnesses using the quantization of `chip`.
+pub fn load_coefficients<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    coefficients: &[f64],
+) -> Vec<AssignedValue<F>> {
+    chip.load_vector(ctx, coefficients)
+}
+
+/// Returns the linear predictions `x_t^T beta` for every row `t` of the column-wise matrix `x`.
+pub fn linear_predictions<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    beta: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    let n = x.first().map(|column| column.len()).unwrap_or(0);
+    (0..n)
+        .map(|t| chip.inner_product(ctx, x.iter().map(|column| column[t]), beta.iter().copied()))
+        .collect()
+}
+
+/// Constrains `|a - b| <= tolerance` for quantized fixed point values.
+/// The circuit becomes unsatisfiable if the difference is larger than `tolerance`
+/// (in particular when `tolerance` is negative).
+pub fn assert_within_tolerance<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: impl Into<QuantumCell<F>>,
+    b: impl Into<QuantumCell<F>>,
+    tolerance: impl Into<QuantumCell<F>>,
+) {
+    let within = chip.qeq_within(ctx, a, b, tolerance);
+    chip.gate().assert_is_const(ctx, &within, &F::from(1));
+}
+
+/// Loads the claimed coefficients and the tolerance, constrains them to the valid range and the
+/// tolerance to be non-negative, constrains `|computed[i] - claimed[i]| <= tolerance` for every
+/// coefficient and makes the claimed coefficients followed by the tolerance public.
+/// Returns the assigned claimed coefficients.
+pub fn check_coefficients<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    computed: &[AssignedValue<F>],
+    claimed: &[f64],
+    tolerance: f64,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Vec<AssignedValue<F>> {
+    assert_eq!(computed.len(), claimed.len(), "number of claimed coefficients does not match");
+    let claimed = load_coefficients(chip, ctx, claimed);
+    let tolerance = ctx.load_witness(chip.quantization(tolerance));
+    for claimed in claimed.iter() {
+        chip.check_valid_range(ctx, *claimed, "claimed coefficient");
+    }
+    chip.check_valid_range(ctx, tolerance, "tolerance");
+    let tolerance_is_non_neg = chip.qge(ctx, tolerance, QuantumCell::Constant(F::from(0)));
+    chip.gate().assert_is_const(ctx, &tolerance_is_non_neg, &F::from(1));
+    for (computed, claimed) in computed.iter().zip(claimed.iter()) {
+        assert_within_tolerance(chip, ctx, *computed, *claimed, tolerance);
+    }
+    make_public.extend(claimed.iter().copied());
+    make_public.push(tolerance);
+
+    claimed
+}
+
+/// Commits to the quantized dataset with a Poseidon hash over the given cells: the columns of
+/// `x` one after another, followed by `y`. The cells must be the ones used in the regression
+/// arithmetic, otherwise the commitment says nothing about the data the model was fit on.
+pub fn commit_dataset<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let values: Vec<AssignedValue<F>> = x.iter().flatten().chain(y.iter()).copied().collect();
+    poseidon_commit(ctx, chip.gate(), &values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_coefficients;
+    use crate::gadget::testing::{mock_verify, PRECISION};
+
+    #[test]
+    fn test_check_coefficients() {
+        // the computed coefficients are (1.5, -2.25) and the tolerance is 0.01
+        for (claimed, accepted) in
+            [([1.505, -2.25], true), ([1.5, -2.3], false), ([-1.5, -2.25], false)]
+        {
+            let verified = mock_verify(|chip, ctx| {
+                let computed = chip.load_vector(ctx, &[1.5, -2.25]);
+                let mut make_public = vec![];
+                check_coefficients(chip, ctx, &computed, &claimed, 0.01, &mut make_public);
+                assert_eq!(make_public.len(), 3, "the claimed coefficients and the tolerance");
+            });
+            assert_eq!(verified, accepted, "claimed {claimed:?}");
+        }
+    }
+
+    #[test]
+    fn test_claimed_out_of_range() {
+        // rejected even though it matches the computed value
+        let too_large = 2f64.powi(PRECISION as i32 + 8);
+        let verified = mock_verify(|chip, ctx| {
+            let computed = chip.load_vector(ctx, &[1.5, too_large]);
+            check_coefficients(chip, ctx, &computed, &[1.5, too_large], 0.01, &mut vec![]);
+        });
+        assert!(!verified);
+    }
+
+    #[test]
+    fn test_negative_tolerance() {
+        let verified = mock_verify(|chip, ctx| {
+            let computed = chip.load_vector(ctx, &[1.5, -2.25]);
+            check_coefficients(chip, ctx, &computed, &[1.5, -2.25], -0.01, &mut vec![]);
+        });
+        assert!(!verified);
+    }
+}
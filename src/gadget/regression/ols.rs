@@ -0,0 +1,314 @@
+//! Ordinary least squares for an arbitrary number of features.
+//!
+//! The design matrix `x` is given column-wise, i.e. `x[j]` holds the `n` observations of
+//! feature `j`, which matches the layout of the regression input files. Instead of solving for
+//! the coefficients in-circuit, the claimed coefficient vector `beta` is checked either against
+//! the normal equations `(X^T X) beta = X^T y` or against the equivalent first-order optimality
+//! condition `X^T (y - X beta) = 0`, up to a tolerance.
+//!
+//! `X^T X`, `X^T y` and `X^T (y - X beta)` are summed with [`crate::gadget::accumulator`], so the
+//! data must come with [`DataBounds`]: they are constrained in-circuit and circuit construction
+//! panics if they allow a sum to wrap around the field.
+use super::assert_within_tolerance;
+use crate::gadget::accumulator::{self, bound_vector, BoundedValue};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+use serde::{Deserialize, Serialize};
+
+/// How the claimed coefficients are checked. Both modes bound the same quantity
+/// `X^T y - X^T X beta`, so the tolerance has the same meaning for either of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verification {
+    /// Checks `(X^T X) beta = X^T y`: O(n k^2) multiplications and O(k^2) rescales.
+    #[default]
+    NormalEquations,
+    /// Checks `X^T (y - X beta) = 0`: O(n k) multiplications and n + k rescales, no divisions.
+    ResidualOrthogonality,
+}
+
+/// Prepends a column of ones to the design matrix so that `beta[0]` is the intercept.
+pub fn with_intercept<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+) -> Vec<Vec<AssignedValue<F>>> {
+    assert!(!x.is_empty(), "design matrix needs at least one column");
+    let n = x[0].len();
+    let one = ctx.load_constant(chip.quantization(1.0));
+    let mut columns = Vec::with_capacity(x.len() + 1);
+    columns.push(vec![one; n]);
+    columns.extend(x.iter().cloned());
+
+    columns
+}
+
+/// Bounds on the magnitude of the data: `|x[j][t]| <= x[j]` for every column `j` of the design
+/// matrix and `|y[t]| <= y`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataBounds {
+    pub x: Vec<f64>,
+    pub y: f64,
+}
+
+impl DataBounds {
+    /// The same bound `max_abs` for all `k` columns and for `y`.
+    pub fn uniform(k: usize, max_abs: f64) -> Self {
+        Self { x: vec![max_abs; k], y: max_abs }
+    }
+
+    /// The bounds of the design matrix returned by [`with_intercept`].
+    pub fn with_intercept(&self) -> Self {
+        let mut x = vec![1.0];
+        x.extend(self.x.iter().copied());
+        Self { x, y: self.y }
+    }
+
+    /// A bound on the residuals `y - X beta` of `n` observations for least squares, ridge or
+    /// Lasso coefficients: `beta = 0` has a larger loss, so `||y - X beta|| <= ||y|| <= sqrt(n) y`.
+    /// It is doubled for coefficients that are only optimal up to the tolerance.
+    pub fn residual(&self, n: usize) -> f64 {
+        2.0 * (n as f64).sqrt() * self.y
+    }
+}
+
+/// Applies [`bound_vector`] to every column `x[j]` with the bound `max_abs[j]`.
+pub(crate) fn bound_columns<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    max_abs: &[f64],
+) -> Vec<Vec<BoundedValue<F>>> {
+    assert_eq!(x.len(), max_abs.len(), "need one bound per column of the design matrix");
+    x.iter().zip(max_abs.iter()).map(|(column, b)| bound_vector(chip, ctx, column, *b)).collect()
+}
+
+fn bounded_gram_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<BoundedValue<F>>],
+) -> Vec<Vec<AssignedValue<F>>> {
+    let k = x.len();
+    let mut gram: Vec<Vec<Option<AssignedValue<F>>>> = vec![vec![None; k]; k];
+    for i in 0..k {
+        for j in i..k {
+            let entry = accumulator::inner_product(chip, ctx, &x[i], &x[j]);
+            gram[i][j] = Some(entry);
+            gram[j][i] = Some(entry);
+        }
+    }
+
+    gram.into_iter().map(|row| row.into_iter().map(|entry| entry.unwrap()).collect()).collect()
+}
+
+fn bounded_moment_vector<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<BoundedValue<F>>],
+    y: &[BoundedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    x.iter()
+        .map(|column| {
+            assert_eq!(column.len(), y.len(), "every column needs one value per observation");
+            accumulator::inner_product(chip, ctx, column, y)
+        })
+        .collect()
+}
+
+/// Computes `X^T X` (k x k) given the bounds `max_abs` of the columns of `x`, with one rescale
+/// per entry. Only the upper triangle is computed, the matrix is symmetric.
+pub fn gram_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    max_abs: &[f64],
+) -> Vec<Vec<AssignedValue<F>>> {
+    let x = bound_columns(chip, ctx, x, max_abs);
+    bounded_gram_matrix(chip, ctx, &x)
+}
+
+/// Computes `X^T y` (length k) with one rescale per entry.
+pub fn moment_vector<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    bounds: &DataBounds,
+) -> Vec<AssignedValue<F>> {
+    let x = bound_columns(chip, ctx, x, &bounds.x);
+    let y = bound_vector(chip, ctx, y, bounds.y);
+    bounded_moment_vector(chip, ctx, &x, &y)
+}
+
+/// Computes `X^T X` and `X^T y`, range checking every value against `bounds` only once.
+pub fn normal_equations<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    bounds: &DataBounds,
+) -> (Vec<Vec<AssignedValue<F>>>, Vec<AssignedValue<F>>) {
+    let x = bound_columns(chip, ctx, x, &bounds.x);
+    let y = bound_vector(chip, ctx, y, bounds.y);
+    (bounded_gram_matrix(chip, ctx, &x), bounded_moment_vector(chip, ctx, &x, &y))
+}
+
+/// Constrains `|(a beta)[i] - b[i]| <= tolerance` for every row `i` of the square system `a`.
+pub fn check_linear_system<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &[Vec<AssignedValue<F>>],
+    b: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+) {
+    assert_eq!(a.len(), b.len());
+    assert_eq!(a.len(), beta.len());
+    for (row, b_i) in a.iter().zip(b.iter()) {
+        let a_beta_i = chip.inner_product(ctx, row.iter().copied(), beta.iter().copied());
+        assert_within_tolerance(chip, ctx, a_beta_i, *b_i, tolerance);
+    }
+}
+
+/// Constrains `beta` to solve the normal equations `(X^T X) beta = X^T y` up to `tolerance`
+/// (per equation, in the units of `X^T y`), given the bounds of the data.
+pub fn verify_normal_equations<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+    bounds: &DataBounds,
+) {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    let (gram, moment) = normal_equations(chip, ctx, x, y, bounds);
+    check_linear_system(chip, ctx, &gram, &moment, beta, tolerance);
+}
+
+/// Computes `X^T (y - X beta)`, the negated gradient of the least squares loss
+/// `1/2 ||y - X beta||^2`, one entry per column of `x`, given the bounds of the data.
+///
+/// The coefficients are constrained to the valid range, every fitted value `x_t^T beta` is
+/// rescaled once and every residual is constrained to [`DataBounds::residual`], so that each
+/// `X[j]^T r` is an accumulated sum with a single rescale.
+pub fn residual_gradient<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    bounds: &DataBounds,
+) -> Vec<AssignedValue<F>> {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    for column in x.iter() {
+        assert_eq!(column.len(), y.len(), "every column needs one value per observation");
+    }
+    let x = bound_columns(chip, ctx, x, &bounds.x);
+    let y = bound_vector(chip, ctx, y, bounds.y);
+    let beta: Vec<BoundedValue<F>> =
+        beta.iter().map(|b| accumulator::bound_to_valid_range(chip, ctx, *b)).collect();
+    let max_residual = bounds.residual(y.len());
+    let residuals: Vec<BoundedValue<F>> = y
+        .iter()
+        .enumerate()
+        .map(|(t, y_t)| {
+            let row: Vec<BoundedValue<F>> = x.iter().map(|column| column[t].clone()).collect();
+            let fitted = accumulator::inner_product(chip, ctx, &row, &beta);
+            let residual = chip.gate().sub(ctx, y_t.value, fitted);
+            accumulator::bound(chip, ctx, residual, max_residual)
+        })
+        .collect();
+    x.iter().map(|column| accumulator::inner_product(chip, ctx, column, &residuals)).collect()
+}
+
+/// Constrains the residuals `y - X beta` to be orthogonal to every column of `X` up to
+/// `tolerance`, i.e. `|X[j]^T (y - X beta)| <= tolerance`, given the bounds of the data.
+pub fn verify_residual_orthogonality<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+    bounds: &DataBounds,
+) {
+    for gradient in residual_gradient(chip, ctx, x, y, beta, bounds) {
+        assert_within_tolerance(chip, ctx, gradient, Constant(F::from(0)), tolerance);
+    }
+}
+
+/// Checks the claimed coefficients with the given [`Verification`] mode, given the bounds of the
+/// data.
+#[allow(clippy::too_many_arguments)]
+pub fn verify<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+    bounds: &DataBounds,
+    mode: Verification,
+) {
+    match mode {
+        Verification::NormalEquations => {
+            verify_normal_equations(chip, ctx, x, y, beta, tolerance, bounds)
+        }
+        Verification::ResidualOrthogonality => {
+            verify_residual_orthogonality(chip, ctx, x, y, beta, tolerance, bounds)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{residual_gradient, verify, DataBounds, Verification};
+    use crate::gadget::testing::{load_line, mock_verify, LINE_Y};
+
+    #[test]
+    fn test_verify() {
+        // the least squares fit is y = 2.15 + 2.94 x
+        let cases = [([2.15, 2.94], true), ([2.15, 2.95], false), ([2.2, 2.94], false)];
+        for mode in [Verification::NormalEquations, Verification::ResidualOrthogonality] {
+            for (beta, accepted) in cases {
+                let verified = mock_verify(|chip, ctx| {
+                    let (x, y) = load_line(chip, ctx);
+                    let beta = chip.load_vector(ctx, &beta);
+                    let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                    let bounds = DataBounds::uniform(1, 20.0).with_intercept();
+                    verify(chip, ctx, &x, &y, &beta, tolerance, &bounds, mode);
+                });
+                assert_eq!(verified, accepted, "{mode:?}, beta {beta:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_data_out_of_bounds() {
+        // the fit is right, but x = 4 exceeds the declared bound
+        for mode in [Verification::NormalEquations, Verification::ResidualOrthogonality] {
+            let verified = mock_verify(|chip, ctx| {
+                let (x, y) = load_line(chip, ctx);
+                let beta = chip.load_vector(ctx, &[2.15, 2.94]);
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                let bounds = DataBounds { x: vec![1.0, 3.0], y: 20.0 };
+                verify(chip, ctx, &x, &y, &beta, tolerance, &bounds, mode);
+            });
+            assert!(!verified, "{mode:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "every column needs one value per observation")]
+    fn test_residual_gradient_lengths() {
+        mock_verify(|chip, ctx| {
+            let x = chip.load_matrix(ctx, &[vec![1.0, 2.0, 3.0]]);
+            let y = chip.load_vector(ctx, &LINE_Y);
+            let beta = chip.load_vector(ctx, &[2.94]);
+            residual_gradient(chip, ctx, &x, &y, &beta, &DataBounds::uniform(1, 20.0));
+        });
+    }
+}
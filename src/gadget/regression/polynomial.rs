@@ -0,0 +1,104 @@
+//! Polynomial regression `y ~ beta[0] + beta[1] x + ... + beta[d] x^d` of a single feature.
+//!
+//! The feature is expanded into its powers in-circuit and the resulting design matrix is
+//! checked with [`super::ols`], so both verification modes are available.
+use super::ols::{self, DataBounds, Verification};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+/// Returns the columns `[x, x^2, ..., x^degree]`.
+pub fn expand_powers<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    degree: usize,
+) -> Vec<Vec<AssignedValue<F>>> {
+    assert!(degree >= 1, "degree must be at least 1");
+    let mut powers = vec![x.to_vec()];
+    for _ in 1..degree {
+        let next = powers
+            .last()
+            .unwrap()
+            .iter()
+            .zip(x.iter())
+            .map(|(power, x)| chip.qmul(ctx, *power, *x))
+            .collect();
+        powers.push(next);
+    }
+
+    powers
+}
+
+/// Evaluates `beta[0] + beta[1] x + ... + beta[d] x^d` with Horner's method.
+pub fn evaluate<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: AssignedValue<F>,
+    beta: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    // `polynomial` expects the coefficient of the highest power first
+    chip.polynomial(ctx, x, beta.iter().rev().copied())
+}
+
+/// Constrains `beta` (intercept first, `degree + 1` entries) to be the least squares fit of
+/// `y` on the powers of `x` up to `degree`, up to `tolerance`. `bounds` holds the bound of the
+/// single feature and of `y`, the bounds of the powers are derived from it.
+#[allow(clippy::too_many_arguments)]
+pub fn verify<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    degree: usize,
+    tolerance: AssignedValue<F>,
+    bounds: &DataBounds,
+    mode: Verification,
+) {
+    assert_eq!(beta.len(), degree + 1, "need one coefficient per power, including the intercept");
+    assert_eq!(bounds.x.len(), 1, "polynomial regression has a single feature");
+    let powers = expand_powers(chip, ctx, x, degree);
+    let design_matrix = ols::with_intercept(chip, ctx, &powers);
+    // |x^d| <= max_abs^d up to the rounding of the powers, which the slack of 1 absorbs
+    let power_bounds = (1..=degree).map(|d| bounds.x[0].abs().powi(d as i32) + 1.0).collect();
+    let design_bounds = DataBounds { x: power_bounds, y: bounds.y }.with_intercept();
+    ols::verify(chip, ctx, &design_matrix, y, beta, tolerance, &design_bounds, mode);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{evaluate, verify};
+    use crate::gadget::fixed_point::FixedPointChip;
+    use crate::gadget::regression::ols::{DataBounds, Verification};
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+
+    #[test]
+    fn test_verify() {
+        // y = 1 - 2 x + 0.5 x^2 fits exactly
+        let cases =
+            [([1.0, -2.0, 0.5], true), ([1.0, -2.0, 0.51], false), ([1.01, -2.0, 0.5], false)];
+        for mode in [Verification::NormalEquations, Verification::ResidualOrthogonality] {
+            for (beta, accepted) in cases {
+                let verified = mock_verify(|chip, ctx| {
+                    let x = chip.load_vector(ctx, &[-2.0, -1.0, 0.0, 1.0, 2.0, 3.0]);
+                    let y = chip.load_vector(ctx, &[7.0, 3.5, 1.0, -0.5, -1.0, -0.5]);
+                    let beta = chip.load_vector(ctx, &beta);
+                    let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                    let bounds = DataBounds::uniform(1, 10.0);
+                    verify(chip, ctx, &x, &y, &beta, 2, tolerance, &bounds, mode);
+                });
+                assert_eq!(verified, accepted, "{mode:?}, beta {beta:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let output = mock_run(|chip, ctx| {
+            let x = ctx.load_witness(chip.quantization(2.5));
+            let beta = chip.load_vector(ctx, &[1.0, -2.0, 0.5]);
+            vec![evaluate(chip, ctx, x, &beta)]
+        });
+        assert_eq!(FixedPointChip::dequantize(output[0], PRECISION), -0.875);
+    }
+}
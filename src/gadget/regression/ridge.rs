@@ -0,0 +1,70 @@
+//! Ridge (L2-regularized) regression.
+//!
+//! The claimed coefficients are checked against the regularized normal equations
+//! `(X^T X + lambda I) beta = X^T y`, sharing the machinery of [`super::ols`].
+use super::ols::{check_linear_system, normal_equations, DataBounds};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+/// Adds `lambda` to the diagonal of the square matrix `a`, skipping the first entry when
+/// `skip_intercept` is set.
+pub fn add_to_diagonal<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &mut [Vec<AssignedValue<F>>],
+    lambda: AssignedValue<F>,
+    skip_intercept: bool,
+) {
+    let start = if skip_intercept { 1 } else { 0 };
+    for (i, row) in a.iter_mut().enumerate().skip(start) {
+        row[i] = chip.qadd(ctx, row[i], lambda);
+    }
+}
+
+/// Constrains `beta` to solve `(X^T X + lambda I) beta = X^T y` up to `tolerance` per equation.
+/// If `intercept` is set, the first column of `x` is the intercept column
+/// (see [`super::ols::with_intercept`]) and its coefficient is not penalized. `bounds` are the
+/// bounds of the data, see [`super::ols::DataBounds`].
+#[allow(clippy::too_many_arguments)]
+pub fn verify_normal_equations<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    lambda: AssignedValue<F>,
+    tolerance: AssignedValue<F>,
+    intercept: bool,
+    bounds: &DataBounds,
+) {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    let (mut gram, moment) = normal_equations(chip, ctx, x, y, bounds);
+    add_to_diagonal(chip, ctx, &mut gram, lambda, intercept);
+    check_linear_system(chip, ctx, &gram, &moment, beta, tolerance);
+}
+
+#[cfg(test)]
+mod test {
+    use super::verify_normal_equations;
+    use crate::gadget::regression::ols::DataBounds;
+    use crate::gadget::testing::{load_line, mock_verify};
+
+    #[test]
+    fn test_normal_equations() {
+        // with lambda = 1 and an unpenalized intercept, (X^T X + diag(0, 1)) beta = X^T y is
+        // solved by (3.375, 2.45), while the least squares fit is (2.15, 2.94)
+        for (beta, accepted) in
+            [([3.375, 2.45], true), ([3.375, 2.46], false), ([2.15, 2.94], false)]
+        {
+            let verified = mock_verify(|chip, ctx| {
+                let (x, y) = load_line(chip, ctx);
+                let beta = chip.load_vector(ctx, &beta);
+                let lambda = ctx.load_witness(chip.quantization(1.0));
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                let bounds = DataBounds::uniform(1, 20.0).with_intercept();
+                verify_normal_equations(chip, ctx, &x, &y, &beta, lambda, tolerance, true, &bounds);
+            });
+            assert_eq!(verified, accepted, "beta {beta:?}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Standard errors and t-statistics of linear regression coefficients.
+//!
+//! - sigma^2 = RSS / (n - k)
+//! - se[j] = sqrt(sigma^2 * (X^T X)^{-1}[j][j])
+//! - t[j] = beta[j] / se[j]
+//!
+//! `(X^T X)^{-1}` is not computed with divisions in-circuit: the prover supplies the inverse as a
+//! witness and the circuit checks `(X^T X) M = I` up to a tolerance.
+use super::{assert_within_tolerance, ols::gram_matrix};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{
+    utils::{BigPrimeField, ScalarField},
+    AssignedValue, Context,
+};
+
+#[derive(Clone, Debug)]
+pub struct CoefficientStatistics<F: ScalarField> {
+    /// estimated residual variance RSS / (n - k)
+    pub sigma_squared: AssignedValue<F>,
+    /// diagonal of (X^T X)^{-1}
+    pub inverse_diagonal: Vec<AssignedValue<F>>,
+    pub standard_errors: Vec<AssignedValue<F>>,
+    pub t_statistics: Vec<AssignedValue<F>>,
+}
+
+// Gauss-Jordan elimination with partial pivoting, only used to compute the witness
+#[allow(clippy::needless_range_loop)]
+fn invert(mut a: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let k = a.len();
+    let mut inv: Vec<Vec<f64>> =
+        (0..k).map(|i| (0..k).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for col in 0..k {
+        let pivot = (col..k)
+            .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+            .unwrap();
+        assert!(a[pivot][col] != 0.0, "X^T X is singular");
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let p = a[col][col];
+        for j in 0..k {
+            a[col][j] /= p;
+            inv[col][j] /= p;
+        }
+        for i in 0..k {
+            if i != col {
+                let factor = a[i][col];
+                for j in 0..k {
+                    a[i][j] -= factor * a[col][j];
+                    inv[i][j] -= factor * inv[col][j];
+                }
+            }
+        }
+    }
+
+    inv
+}
+
+/// Constrains `a m = I` entrywise up to `tolerance` for the square matrices `a` and `m`.
+pub fn check_inverse<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &[Vec<AssignedValue<F>>],
+    m: &[Vec<AssignedValue<F>>],
+    tolerance: AssignedValue<F>,
+) {
+    let k = a.len();
+    assert_eq!(m.len(), k, "need square matrices of the same size");
+    for (i, row) in a.iter().enumerate() {
+        for j in 0..k {
+            let entry = chip.inner_product(ctx, row.iter().copied(), m.iter().map(|r| r[j]));
+            let identity = if i == j { chip.quantization(1.0) } else { F::from(0) };
+            assert_within_tolerance(chip, ctx, entry, Constant(identity), tolerance);
+        }
+    }
+}
+
+/// Loads the inverse of the square matrix `a` as a witness and constrains `a M = I`
+/// entrywise up to `tolerance` (see [`check_inverse`]). Returns `M`.
+pub fn inverse<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    a: &[Vec<AssignedValue<F>>],
+    tolerance: AssignedValue<F>,
+) -> Vec<Vec<AssignedValue<F>>> {
+    let a_native: Vec<Vec<f64>> = a
+        .iter()
+        .map(|row| row.iter().map(|v| chip.dequantization(*v.value())).collect())
+        .collect();
+    let inverse = chip.load_matrix(ctx, &invert(a_native));
+    check_inverse(chip, ctx, a, &inverse, tolerance);
+
+    inverse
+}
+
+/// Computes sigma^2, the diagonal of `(X^T X)^{-1}`, the standard errors and the t-statistics
+/// of the coefficients `beta`, given the residual sum of squares `rss` of the model
+/// (see [`super::fit::residual_sum_of_squares`]). `inverse_tolerance` bounds the entrywise
+/// error of `(X^T X) M = I` and `max_abs` holds the bounds of the columns of `x`
+/// (see [`super::ols::DataBounds`]). `rss` and the t-statistics must be in the valid range of
+/// `qdiv`, so a perfect fit (zero standard errors) makes the circuit unsatisfiable.
+pub fn coefficient_statistics<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    max_abs: &[f64],
+    beta: &[AssignedValue<F>],
+    rss: AssignedValue<F>,
+    inverse_tolerance: AssignedValue<F>,
+) -> CoefficientStatistics<F> {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    let n = x[0].len();
+    let k = x.len();
+    assert!(n > k, "need more observations than coefficients");
+
+    // n - k only depends on the shape of the circuit
+    let dof = Constant(chip.quantization((n - k) as f64));
+    let sigma_squared = chip.qdiv(ctx, rss, dof);
+
+    let gram = gram_matrix(chip, ctx, x, max_abs);
+    let gram_inverse = inverse(chip, ctx, &gram, inverse_tolerance);
+    let inverse_diagonal: Vec<AssignedValue<F>> =
+        gram_inverse.iter().enumerate().map(|(j, row)| row[j]).collect();
+
+    let standard_errors: Vec<AssignedValue<F>> = inverse_diagonal
+        .iter()
+        .map(|m_jj| {
+            let variance = chip.qmul(ctx, sigma_squared, *m_jj);
+            chip.qsqrt(ctx, variance)
+        })
+        .collect();
+    let t_statistics: Vec<AssignedValue<F>> = beta
+        .iter()
+        .zip(standard_errors.iter())
+        .map(|(b, se)| chip.qdiv(ctx, *b, *se))
+        .collect();
+
+    CoefficientStatistics { sigma_squared, inverse_diagonal, standard_errors, t_statistics }
+}
+
+/// Returns the selection mask of the coefficient `indices` among `k` coefficients, or an error
+/// if an index is out of range.
+pub fn selection_mask(k: usize, indices: &[usize]) -> Result<Vec<bool>, String> {
+    let mut mask = vec![false; k];
+    for &i in indices.iter() {
+        if i >= k {
+            return Err(format!("coefficient index {i} is out of range for {k} coefficients"));
+        }
+        mask[i] = true;
+    }
+
+    Ok(mask)
+}
+
+/// Constrains `|t| >= threshold`, e.g. to prove that a coefficient is significant.
+pub fn assert_significant<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    t: AssignedValue<F>,
+    threshold: AssignedValue<F>,
+) {
+    let t_abs = chip.qabs(ctx, t);
+    let is_significant = chip.qge(ctx, t_abs, threshold);
+    chip.gate().assert_is_const(ctx, &is_significant, &F::from(1));
+}
+
+/// Constrains `|t[i]| >= threshold` for every `i` with `selected[i] = 1` and every `selected[i]`
+/// to be a bit. The number of constraints does not depend on the selection, so `selected` can
+/// be public and chosen after key generation.
+pub fn assert_selected_significant<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    t: &[AssignedValue<F>],
+    selected: &[AssignedValue<F>],
+    threshold: AssignedValue<F>,
+) {
+    assert_eq!(t.len(), selected.len(), "need one selector per t-statistic");
+    for (t, selected) in t.iter().zip(selected.iter()) {
+        chip.gate().assert_bit(ctx, *selected);
+        let t_abs = chip.qabs(ctx, *t);
+        let is_significant = chip.qge(ctx, t_abs, threshold);
+        let is_not_significant = chip.gate().not(ctx, is_significant);
+        let violated = chip.gate().and(ctx, *selected, is_not_significant);
+        chip.gate().assert_is_const(ctx, &violated, &F::from(0));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        assert_selected_significant, check_inverse, coefficient_statistics, inverse, selection_mask,
+    };
+    use crate::gadget::fixed_point::FixedPointChip;
+    use crate::gadget::regression::fit::residual_sum_of_squares;
+    use crate::gadget::testing::{load_line, mock_run, mock_verify, PRECISION};
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_selection_mask() {
+        assert_eq!(selection_mask(3, &[2, 0]), Ok(vec![true, false, true]));
+        assert!(selection_mask(3, &[3]).is_err());
+    }
+    #[test]
+    fn test_inverse() {
+        // the inverse of [[2, 1], [1, 3]] is [[0.6, -0.2], [-0.2, 0.4]]
+        let a = [vec![2.0, 1.0], vec![1.0, 3.0]];
+        let outputs = mock_run(|chip, ctx| {
+            let a = chip.load_matrix(ctx, &a);
+            let tolerance = ctx.load_witness(chip.quantization(1e-6));
+            inverse(chip, ctx, &a, tolerance).concat()
+        });
+        for (output, expected) in outputs.iter().zip([0.6, -0.2, -0.2, 0.4]) {
+            let output = FixedPointChip::dequantize(*output, PRECISION);
+            assert!((output - expected).abs() < 1e-6, "{output} != {expected}");
+        }
+
+        let cases = [([[0.6, -0.2], [-0.2, 0.4]], true), ([[0.6, -0.2], [-0.2, 0.41]], false)];
+        for (m, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let a = chip.load_matrix(ctx, &a);
+                let m = chip.load_matrix(ctx, &m.map(|row| row.to_vec()));
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                check_inverse(chip, ctx, &a, &m, tolerance);
+            });
+            assert_eq!(verified, accepted, "m {m:?}");
+        }
+    }
+
+    #[test]
+    fn test_coefficient_statistics() {
+        // y = 2.15 + 2.94 x has RSS = 0.082 with n - k = 2, and
+        // (X^T X)^{-1} = [[1.5, -0.5], [-0.5, 0.2]]
+        let outputs = mock_run(|chip, ctx| {
+            let (x, y) = load_line(chip, ctx);
+            let beta = chip.load_vector(ctx, &[2.15, 2.94]);
+            let rss = residual_sum_of_squares(chip, ctx, &x, &y, &beta);
+            let tolerance = ctx.load_witness(chip.quantization(1e-6));
+            let statistics =
+                coefficient_statistics(chip, ctx, &x, &[1.0, 4.0], &beta, rss, tolerance);
+            [
+                vec![statistics.sigma_squared],
+                statistics.inverse_diagonal,
+                statistics.standard_errors,
+                statistics.t_statistics,
+            ]
+            .concat()
+        });
+        let expected = [
+            0.041,
+            1.5,
+            0.2,
+            0.24799193535274489,
+            0.09055385138137417,
+            8.669636764364252,
+            32.46686866600488,
+        ];
+        for (output, expected) in outputs.iter().zip(expected) {
+            let output = FixedPointChip::dequantize(*output, PRECISION);
+            assert!((output - expected).abs() < 1e-5, "{output} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_assert_selected_significant() {
+        // the t-statistics of y = 2.15 + 2.94 x, the intercept is not significant at 10
+        let cases = [([0u64, 1], true), ([0, 0], true), ([1, 1], false), ([0, 2], false)];
+        for (mask, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let t = chip.load_vector(ctx, &[8.669636764364252, 32.46686866600488]);
+                let selected: Vec<_> =
+                    mask.iter().map(|s| ctx.load_witness(Fr::from(*s))).collect();
+                let threshold = ctx.load_witness(chip.quantization(10.0));
+                assert_selected_significant(chip, ctx, &t, &selected, threshold);
+            });
+            assert_eq!(verified, accepted, "mask {mask:?}");
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Weighted least squares with per-observation weights.
+//!
+//! The claimed coefficients are checked against the weighted normal equations
+//! `(X^T W X) beta = X^T W y`, where `W = diag(w)`. The weights are constrained to be
+//! non-negative, they should still be public or committed.
+//!
+//! `W X` and `W y` are rescaled once per entry and the products with `X` are summed with
+//! [`crate::gadget::accumulator`], so the data and the weights must come with
+//! [`WeightedDataBounds`].
+use super::ols::{bound_columns, check_linear_system, DataBounds};
+use crate::gadget::accumulator::{self, bound_vector, BoundedValue};
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+/// [`DataBounds`] of the design matrix and `y`, and the bound `w[t] <= w` of the weights.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedDataBounds {
+    pub data: DataBounds,
+    pub w: f64,
+}
+
+// w * x[j] elementwise, each product rescaled once
+fn weighted_columns<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<BoundedValue<F>>],
+    w: &[BoundedValue<F>],
+) -> Vec<Vec<BoundedValue<F>>> {
+    x.iter()
+        .map(|column| {
+            assert_eq!(column.len(), w.len(), "need one weight per observation");
+            column
+                .iter()
+                .zip(w.iter())
+                .map(|(x, w)| accumulator::product(chip, ctx, w, x))
+                .collect()
+        })
+        .collect()
+}
+
+fn bounded_weighted_gram_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<BoundedValue<F>>],
+    w: &[BoundedValue<F>],
+) -> Vec<Vec<AssignedValue<F>>> {
+    let weighted = weighted_columns(chip, ctx, x, w);
+    let k = x.len();
+    let mut gram: Vec<Vec<Option<AssignedValue<F>>>> = vec![vec![None; k]; k];
+    for i in 0..k {
+        for j in i..k {
+            let entry = accumulator::inner_product(chip, ctx, &weighted[i], &x[j]);
+            gram[i][j] = Some(entry);
+            gram[j][i] = Some(entry);
+        }
+    }
+
+    gram.into_iter().map(|row| row.into_iter().map(|entry| entry.unwrap()).collect()).collect()
+}
+
+fn bounded_weighted_moment_vector<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<BoundedValue<F>>],
+    y: &[BoundedValue<F>],
+    w: &[BoundedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    let weighted_y = weighted_columns(chip, ctx, &[y.to_vec()], w).remove(0);
+    x.iter().map(|column| accumulator::inner_product(chip, ctx, column, &weighted_y)).collect()
+}
+
+/// Computes `X^T W X` (k x k) given the bounds `max_abs` of the columns of `x` and the bound
+/// `max_weight` of the weights.
+pub fn weighted_gram_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    w: &[AssignedValue<F>],
+    max_abs: &[f64],
+    max_weight: f64,
+) -> Vec<Vec<AssignedValue<F>>> {
+    let x = bound_columns(chip, ctx, x, max_abs);
+    let w = bound_vector(chip, ctx, w, max_weight);
+    bounded_weighted_gram_matrix(chip, ctx, &x, &w)
+}
+
+/// Computes `X^T W y` (length k) given the bounds of the data and of the weights.
+pub fn weighted_moment_vector<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    w: &[AssignedValue<F>],
+    bounds: &WeightedDataBounds,
+) -> Vec<AssignedValue<F>> {
+    assert_eq!(y.len(), w.len(), "need one weight per observation");
+    let x = bound_columns(chip, ctx, x, &bounds.data.x);
+    let y = bound_vector(chip, ctx, y, bounds.data.y);
+    let w = bound_vector(chip, ctx, w, bounds.w);
+    bounded_weighted_moment_vector(chip, ctx, &x, &y, &w)
+}
+
+/// Constrains every weight to be non-negative.
+pub fn assert_non_negative<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    w: &[AssignedValue<F>],
+) {
+    for w in w.iter() {
+        let is_non_negative = chip.qge(ctx, *w, Constant(F::from(0)));
+        chip.gate().assert_is_const(ctx, &is_non_negative, &F::from(1));
+    }
+}
+
+/// Constrains `beta` to solve `(X^T W X) beta = X^T W y` up to `tolerance` per equation and
+/// the weights to be in `[0, bounds.w]`, given the bounds of the data.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_normal_equations<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    y: &[AssignedValue<F>],
+    w: &[AssignedValue<F>],
+    beta: &[AssignedValue<F>],
+    tolerance: AssignedValue<F>,
+    bounds: &WeightedDataBounds,
+) {
+    assert_eq!(x.len(), beta.len(), "need one coefficient per column of the design matrix");
+    assert_eq!(y.len(), w.len(), "need one weight per observation");
+    assert_non_negative(chip, ctx, w);
+    let x = bound_columns(chip, ctx, x, &bounds.data.x);
+    let y = bound_vector(chip, ctx, y, bounds.data.y);
+    let w = bound_vector(chip, ctx, w, bounds.w);
+    let gram = bounded_weighted_gram_matrix(chip, ctx, &x, &w);
+    let moment = bounded_weighted_moment_vector(chip, ctx, &x, &y, &w);
+    check_linear_system(chip, ctx, &gram, &moment, beta, tolerance);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{verify_normal_equations, WeightedDataBounds};
+    use crate::gadget::regression::ols::{with_intercept, DataBounds};
+    use crate::gadget::testing::mock_verify;
+
+    #[test]
+    fn test_normal_equations() {
+        // y = 2 + 3 x fits exactly, so the coefficients solve the normal equations for any
+        // weights in [0, 2]
+        let cases = [
+            ([1.0, 0.5, 2.0, 0.0], [2.0, 3.0], true),
+            ([1.0, 0.5, 2.0, 0.0], [2.0, 3.01], false),
+            ([1.0, -0.5, 2.0, 1.0], [2.0, 3.0], false),
+            // a weight above its bound
+            ([1.0, 0.5, 2.5, 1.0], [2.0, 3.0], false),
+        ];
+        for (weights, beta, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let x = chip.load_matrix(ctx, &[vec![1.0, 2.0, 3.0, 4.0]]);
+                let y = chip.load_vector(ctx, &[5.0, 8.0, 11.0, 14.0]);
+                let w = chip.load_vector(ctx, &weights);
+                let beta = chip.load_vector(ctx, &beta);
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                let x = with_intercept(chip, ctx, &x);
+                let data = DataBounds::uniform(1, 20.0).with_intercept();
+                let bounds = WeightedDataBounds { data, w: 2.0 };
+                verify_normal_equations(chip, ctx, &x, &y, &w, &beta, tolerance, &bounds);
+            });
+            assert_eq!(verified, accepted, "weights {weights:?}, beta {beta:?}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Descriptive statistics of assigned fixed-point vectors, built on top of the `FixedPointChip`.
+//!
+//! Deviations from the mean are multiplied without rescaling and the sums of products are
+//! rescaled once, so a (co)variance costs one rescale and one division regardless of `n`.
+//! `qdiv` needs its dividend in the valid range, so the sums (`n` times the mean, `d` times the
+//! (co)variance) must stay below `2^precision_bits` in magnitude, also without overflow checks.
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::QuantumCell::Constant;
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+use serde::{Deserialize, Serialize};
+
+pub mod order;
+
+/// Denominator of the variance and covariance estimators.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Divides by `n`.
+    #[default]
+    Population,
+    /// Divides by `n - 1` (Bessel's correction).
+    Sample,
+}
+
+impl Normalization {
+    fn divisor(&self, n: usize) -> usize {
+        match self {
+            Normalization::Population => {
+                assert!(n > 0, "need at least one observation");
+                n
+            }
+            Normalization::Sample => {
+                assert!(n > 1, "need at least two observations for the sample estimator");
+                n - 1
+            }
+        }
+    }
+}
+
+/// Computes `sum x / n`.
+pub fn mean<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    assert!(!x.is_empty(), "need at least one observation");
+    let sum = chip.qsum(ctx, x.iter().copied());
+    let n = Constant(chip.quantization(x.len() as f64));
+
+    chip.qdiv(ctx, sum, n)
+}
+
+/// Returns `x - mean(x)` elementwise.
+pub fn deviations<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    let mean = mean(chip, ctx, x);
+    x.iter().map(|x| chip.qsub(ctx, *x, mean)).collect()
+}
+
+// sum dx dy / divisor for deviations `dx`, `dy` from the respective means
+fn normalized_inner_product<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    dx: &[AssignedValue<F>],
+    dy: &[AssignedValue<F>],
+    normalization: Normalization,
+) -> AssignedValue<F> {
+    assert_eq!(dx.len(), dy.len(), "need the same number of observations");
+    let divisor = Constant(chip.quantization(normalization.divisor(dx.len()) as f64));
+    let sum = chip.inner_product(ctx, dx.iter().copied(), dy.iter().copied());
+
+    chip.qdiv(ctx, sum, divisor)
+}
+
+/// Computes the variance `sum (x - mean(x))^2 / d` with `d` given by `normalization`.
+pub fn variance<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    normalization: Normalization,
+) -> AssignedValue<F> {
+    let dx = deviations(chip, ctx, x);
+    normalized_inner_product(chip, ctx, &dx, &dx, normalization)
+}
+
+/// Computes the standard deviation `sqrt(variance(x))`, which is zero if `x` is constant.
+pub fn std_dev<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    normalization: Normalization,
+) -> AssignedValue<F> {
+    let variance = variance(chip, ctx, x, normalization);
+    chip.qsqrt(ctx, variance)
+}
+
+/// Computes the covariance `sum (x - mean(x)) (y - mean(y)) / d`.
+pub fn covariance<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    y: &[AssignedValue<F>],
+    normalization: Normalization,
+) -> AssignedValue<F> {
+    let dx = deviations(chip, ctx, x);
+    let dy = deviations(chip, ctx, y);
+    normalized_inner_product(chip, ctx, &dx, &dy, normalization)
+}
+
+/// Computes the k x k covariance matrix of the columns `x[0], ..., x[k-1]`.
+/// The means are computed once per column and only the upper triangle is computed.
+pub fn covariance_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[Vec<AssignedValue<F>>],
+    normalization: Normalization,
+) -> Vec<Vec<AssignedValue<F>>> {
+    let dx: Vec<Vec<AssignedValue<F>>> =
+        x.iter().map(|column| deviations(chip, ctx, column)).collect();
+    let k = x.len();
+    let mut covariance: Vec<Vec<Option<AssignedValue<F>>>> = vec![vec![None; k]; k];
+    for i in 0..k {
+        for j in i..k {
+            let entry = normalized_inner_product(chip, ctx, &dx[i], &dx[j], normalization);
+            covariance[i][j] = Some(entry);
+            covariance[j][i] = Some(entry);
+        }
+    }
+
+    covariance
+        .into_iter()
+        .map(|row| row.into_iter().map(|entry| entry.unwrap()).collect())
+        .collect()
+}
+
+/// Computes the Pearson correlation `cov(x, y) / (std(x) std(y))`.
+/// The normalization cancels out, so only sums of products are computed. Neither `x` nor `y`
+/// may be constant, which would divide by zero.
+pub fn correlation<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    y: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    assert_eq!(x.len(), y.len(), "need the same number of observations");
+    let dx = deviations(chip, ctx, x);
+    let dy = deviations(chip, ctx, y);
+    let sxy = chip.inner_product(ctx, dx.iter().copied(), dy.iter().copied());
+    let sxx = chip.inner_product(ctx, dx.iter().copied(), dx.iter().copied());
+    let syy = chip.inner_product(ctx, dy.iter().copied(), dy.iter().copied());
+    // sqrt(sxx) sqrt(syy) instead of sqrt(sxx syy) keeps the intermediate values in range
+    let sx = chip.qsqrt(ctx, sxx);
+    let sy = chip.qsqrt(ctx, syy);
+    let denom = chip.qmul(ctx, sx, sy);
+
+    chip.qdiv(ctx, sxy, denom)
+}
+
+/// Converts a covariance matrix into the Pearson correlation matrix
+/// `r[i][j] = c[i][j] / sqrt(c[i][i] c[j][j])`, taking one square root per column.
+/// The diagonal is the constant one. No column may be constant, which would divide by zero.
+pub fn correlation_matrix<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    covariance: &[Vec<AssignedValue<F>>],
+) -> Vec<Vec<AssignedValue<F>>> {
+    let k = covariance.len();
+    let std_devs: Vec<AssignedValue<F>> =
+        covariance.iter().enumerate().map(|(i, row)| chip.qsqrt(ctx, row[i])).collect();
+    let one = ctx.load_constant(chip.quantization(1.0));
+    let mut correlation: Vec<Vec<AssignedValue<F>>> = vec![vec![one; k]; k];
+    for i in 0..k {
+        for j in (i + 1)..k {
+            let denom = chip.qmul(ctx, std_devs[i], std_devs[j]);
+            let entry = chip.qdiv(ctx, covariance[i][j], denom);
+            correlation[i][j] = entry;
+            correlation[j][i] = entry;
+        }
+    }
+
+    correlation
+}
+
+#[cfg(test)]
+mod test {
+    use super::{correlation, covariance, mean, std_dev, variance, Normalization};
+    use crate::gadget::fixed_point::FixedPointChip;
+    use crate::gadget::regression::assert_within_tolerance;
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+
+    const X: [f64; 8] = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    const Y: [f64; 8] = [1.0, 3.0, 2.0, 5.0, 4.0, 6.0, 8.0, 9.0];
+
+    #[test]
+    fn test_moments() {
+        let outputs = mock_run(|chip, ctx| {
+            let x = chip.load_vector(ctx, &X);
+            let y = chip.load_vector(ctx, &Y);
+            vec![
+                mean(chip, ctx, &x),
+                variance(chip, ctx, &x, Normalization::Population),
+                variance(chip, ctx, &x, Normalization::Sample),
+                std_dev(chip, ctx, &x, Normalization::Population),
+                covariance(chip, ctx, &x, &y, Normalization::Population),
+                covariance(chip, ctx, &x, &y, Normalization::Sample),
+                correlation(chip, ctx, &x, &y),
+            ]
+        });
+        let expected = [5.0, 4.0, 32.0 / 7.0, 2.0, 4.875, 39.0 / 7.0, 0.9254290458586866];
+        for (output, expected) in outputs.iter().zip(expected) {
+            let output = FixedPointChip::dequantize(*output, PRECISION);
+            assert!((output - expected).abs() < 1e-6, "{output} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_claimed_moments() {
+        // the claimed population mean and variance, the sample variance is 32 / 7
+        let cases = [(5.0, 4.0, true), (5.01, 4.0, false), (5.0, 32.0 / 7.0, false)];
+        for (claimed_mean, claimed_variance, accepted) in cases {
+            let verified = mock_verify(|chip, ctx| {
+                let x = chip.load_vector(ctx, &X);
+                let tolerance = ctx.load_witness(chip.quantization(1e-6));
+                let m = mean(chip, ctx, &x);
+                let claimed_mean = ctx.load_witness(chip.quantization(claimed_mean));
+                assert_within_tolerance(chip, ctx, m, claimed_mean, tolerance);
+                let v = variance(chip, ctx, &x, Normalization::Population);
+                let claimed_variance = ctx.load_witness(chip.quantization(claimed_variance));
+                assert_within_tolerance(chip, ctx, v, claimed_variance, tolerance);
+            });
+            assert_eq!(verified, accepted, "mean {claimed_mean}, variance {claimed_variance}");
+        }
+    }
+}
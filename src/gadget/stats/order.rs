@@ -0,0 +1,165 @@
+//! Order statistics (median, quantiles) of a private vector via an in-circuit sorting argument.
+//!
+//! The prover supplies the sorted values as witnesses. [`sort`] constrains them to be
+//! - a permutation of the input: `prod (gamma - x[i]) = prod (gamma - s[i])`, where the
+//!   challenge `gamma` is the Poseidon hash of both vectors (Fiat-Shamir), so a prover who
+//!   changes any value cannot predict `gamma`, and
+//! - non-decreasing: `s[i] <= s[i + 1]` for every `i`.
+//!
+//! Quantiles are then read off the sorted vector at positions that only depend on `n`.
+//! [`constrain_sorted`] applies both constraints to a sorted vector supplied by the caller.
+use crate::gadget::commitment::poseidon_commit;
+use crate::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+use halo2_base::gates::GateInstructions;
+use halo2_base::QuantumCell::{Constant, Existing};
+use halo2_base::{utils::BigPrimeField, AssignedValue, Context};
+
+// prod (gamma - x[i])
+fn grand_product<F: BigPrimeField>(
+    gate: &impl GateInstructions<F>,
+    ctx: &mut Context<F>,
+    gamma: AssignedValue<F>,
+    x: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let mut product = ctx.load_constant(F::from(1));
+    for x in x.iter() {
+        let term = gate.sub(ctx, gamma, *x);
+        product = gate.mul(ctx, product, term);
+    }
+
+    product
+}
+
+/// Returns `x` sorted in non-decreasing order, constrained to be a permutation of `x`.
+pub fn sort<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    assert!(!x.is_empty(), "need at least one observation");
+    let mut values: Vec<F> = x.iter().map(|x| *x.value()).collect();
+    values.sort_by_cached_key(|v| FixedPointChip::signed_quantized(*v, chip.precision_bits));
+    let sorted: Vec<AssignedValue<F>> = values.into_iter().map(|v| ctx.load_witness(v)).collect();
+    constrain_sorted(chip, ctx, x, &sorted);
+
+    sorted
+}
+
+/// Constrains `sorted` to be a permutation of `x` in non-decreasing order.
+pub fn constrain_sorted<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    x: &[AssignedValue<F>],
+    sorted: &[AssignedValue<F>],
+) {
+    assert_eq!(x.len(), sorted.len(), "a permutation has the same length");
+    // multiset equality with a challenge derived from both vectors
+    let transcript: Vec<AssignedValue<F>> = x.iter().chain(sorted.iter()).copied().collect();
+    let gamma = poseidon_commit(ctx, chip.gate(), &transcript);
+    let lhs = grand_product(chip.gate(), ctx, gamma, x);
+    let rhs = grand_product(chip.gate(), ctx, gamma, sorted);
+    ctx.constrain_equal(&lhs, &rhs);
+
+    // order
+    for pair in sorted.windows(2) {
+        let is_ordered = chip.qle(ctx, pair[0], pair[1]);
+        chip.gate().assert_is_const(ctx, &is_ordered, &F::from(1));
+    }
+}
+
+/// Returns the `q`-quantile (`0 <= q <= 1`) of the output of [`sort`], linearly interpolating
+/// between the two closest ranks like numpy's default `linear` method:
+/// `s[i] + (h - i) (s[i + 1] - s[i])` with `h = (n - 1) q` and `i = floor(h)`.
+pub fn quantile<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    sorted: &[AssignedValue<F>],
+    q: f64,
+) -> AssignedValue<F> {
+    assert!(!sorted.is_empty(), "need at least one observation");
+    assert!((0.0..=1.0).contains(&q), "quantile must be in [0, 1]");
+    let h = (sorted.len() - 1) as f64 * q;
+    let i = h.floor() as usize;
+    let frac = h - i as f64;
+    if i + 1 == sorted.len() || frac == 0.0 {
+        return sorted[i];
+    }
+    let gap = chip.qsub(ctx, sorted[i + 1], sorted[i]);
+    let offset = chip.qmul(ctx, Existing(gap), Constant(chip.quantization(frac)));
+
+    chip.qadd(ctx, sorted[i], offset)
+}
+
+/// Returns the median of the output of [`sort`], i.e. the mean of the two middle values if
+/// `n` is even.
+pub fn median<F: BigPrimeField>(
+    chip: &FixedPointChip<F>,
+    ctx: &mut Context<F>,
+    sorted: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    quantile(chip, ctx, sorted, 0.5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{constrain_sorted, quantile, sort};
+    use crate::gadget::native::FixedPoint;
+    use crate::gadget::testing::{mock_run, mock_verify, PRECISION};
+
+    type Fixed = FixedPoint<PRECISION>;
+
+    #[test]
+    fn test_sort() {
+        let x = [3.5, -1.25, 7.0, 0.5, -4.0, 0.5];
+        let sorted = mock_run(|chip, ctx| {
+            let x = chip.load_vector(ctx, &x);
+            sort(chip, ctx, &x)
+        });
+        let mut expected = x.to_vec();
+        expected.sort_by(f64::total_cmp);
+        for (output, expected) in sorted.into_iter().zip(expected) {
+            assert_eq!(Fixed::from_field(output), Fixed::quantize(expected));
+        }
+    }
+
+    #[test]
+    fn test_tampered_sort() {
+        let x = [3.0, 1.0, 2.0];
+        let claims = [
+            ([1.0, 2.0, 3.0], true),
+            // a permutation, but not sorted
+            ([1.0, 3.0, 2.0], false),
+            // sorted, but 2 is replaced by a duplicate of 1
+            ([1.0, 1.0, 3.0], false),
+        ];
+        for (sorted, accepted) in claims {
+            let verified = mock_verify(|chip, ctx| {
+                let x = chip.load_vector(ctx, &x);
+                let sorted = chip.load_vector(ctx, &sorted);
+                constrain_sorted(chip, ctx, &x, &sorted);
+            });
+            assert_eq!(verified, accepted, "sorted {sorted:?}");
+        }
+    }
+
+    #[test]
+    fn test_quantile() {
+        // numpy.quantile(x, q) with the default linear method, for odd and even n
+        let qs = [0.0, 0.25, 0.5, 0.9, 1.0];
+        let cases = [
+            (vec![3.5, -1.25, 7.0, 0.5, -4.0], [-4.0, -1.25, 0.5, 5.6, 7.0]),
+            (vec![2.0, -3.5, 10.25, 0.0, -0.75, 6.5], [-3.5, -0.5625, 1.0, 8.375, 10.25]),
+        ];
+        for (x, expected) in cases {
+            let outputs = mock_run(|chip, ctx| {
+                let x = chip.load_vector(ctx, &x);
+                let sorted = sort(chip, ctx, &x);
+                qs.iter().map(|q| quantile(chip, ctx, &sorted, *q)).collect()
+            });
+            for ((q, output), expected) in qs.iter().zip(outputs).zip(expected) {
+                let err = Fixed::from_field(output).to_f64() - expected;
+                assert!(err.abs() < 1e-8, "quantile {q} of {x:?} error {err}");
+            }
+        }
+    }
+}
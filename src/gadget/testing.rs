@@ -0,0 +1,103 @@
+//! Mock prover helpers shared by the gadget tests.
+use crate::gadget::fixed_point::FixedPointChip;
+use crate::gadget::regression::ols::with_intercept;
+use halo2_base::gates::circuit::{builder::BaseCircuitBuilder, CircuitBuilderStage};
+use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use halo2_base::{AssignedValue, Context};
+
+pub(crate) const K: usize = 16;
+/// Precision of the gadget tests that do not depend on it.
+pub(crate) const PRECISION: u32 = 32;
+
+fn mock_builder() -> BaseCircuitBuilder<Fr> {
+    let mut builder = BaseCircuitBuilder::<Fr>::from_stage(CircuitBuilderStage::Mock);
+    builder.set_k(K);
+    builder.set_lookup_bits(K - 1);
+    builder
+}
+
+/// Runs `f` in the mock prover with a default chip of precision `PRECISION`, asserts that the
+/// circuit is satisfied and returns the values of the cells `f` returns.
+pub(crate) fn mock_run(
+    f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>) -> Vec<AssignedValue<Fr>>,
+) -> Vec<Fr> {
+    mock_run_with(PRECISION, |chip| chip, f)
+}
+
+/// Same as [`mock_run`] with a default chip of precision `precision`.
+pub(crate) fn mock_run_at(
+    precision: u32,
+    f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>) -> Vec<AssignedValue<Fr>>,
+) -> Vec<Fr> {
+    mock_run_with(precision, |chip| chip, f)
+}
+
+/// Same as [`mock_run`] with the chip of precision `precision` returned by `configure`, e.g. in
+/// another rounding mode.
+pub(crate) fn mock_run_with(
+    precision: u32,
+    configure: impl FnOnce(FixedPointChip<Fr>) -> FixedPointChip<Fr>,
+    f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>) -> Vec<AssignedValue<Fr>>,
+) -> Vec<Fr> {
+    let mut builder = mock_builder();
+    let chip = configure(FixedPointChip::new(&mut builder, precision));
+    let outputs = f(&chip, builder.main(0));
+    builder.calculate_params(Some(20));
+    MockProver::run(K as u32, &builder, vec![]).unwrap().assert_satisfied();
+
+    outputs.iter().map(|x| *x.value()).collect()
+}
+
+/// Builds the circuit of `f` with a default chip and returns whether the mock prover accepts
+/// it, for circuits that must be unsatisfiable.
+pub(crate) fn mock_verify(f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>)) -> bool {
+    mock_verify_with(PRECISION, |chip| chip, f)
+}
+
+/// Same as [`mock_verify`] with a default chip of precision `precision`.
+pub(crate) fn mock_verify_at(
+    precision: u32,
+    f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>),
+) -> bool {
+    mock_verify_with(precision, |chip| chip, f)
+}
+
+/// Same as [`mock_verify`] with the chip of precision `precision` returned by `configure`.
+pub(crate) fn mock_verify_with(
+    precision: u32,
+    configure: impl FnOnce(FixedPointChip<Fr>) -> FixedPointChip<Fr>,
+    f: impl FnOnce(&FixedPointChip<Fr>, &mut Context<Fr>),
+) -> bool {
+    let mut builder = mock_builder();
+    let chip = configure(FixedPointChip::new(&mut builder, precision));
+    f(&chip, builder.main(0));
+    builder.calculate_params(Some(20));
+
+    MockProver::run(K as u32, &builder, vec![]).unwrap().verify().is_ok()
+}
+
+/// Runs a test that is generic over the precision, `$test::<P>()`, at each precision covered by
+/// the chip tests: the small 16 and 24, the default of the gadget tests and the maximum 63.
+macro_rules! for_each_precision {
+    ($test:ident) => {{
+        $test::<16>();
+        $test::<24>();
+        $test::<32>();
+        $test::<63>();
+    }};
+}
+pub(crate) use for_each_precision;
+
+/// One feature whose least squares fit is `y = 2.15 + 2.94 x`, shared by the regression tests.
+pub(crate) const LINE_X: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+pub(crate) const LINE_Y: [f64; 4] = [5.1, 7.9, 11.2, 13.8];
+
+/// Loads [`LINE_X`] as a design matrix with an intercept column, and [`LINE_Y`].
+pub(crate) fn load_line(
+    chip: &FixedPointChip<Fr>,
+    ctx: &mut Context<Fr>,
+) -> (Vec<Vec<AssignedValue<Fr>>>, Vec<AssignedValue<Fr>>) {
+    let x = chip.load_matrix(ctx, &[LINE_X.to_vec()]);
+    let y = chip.load_vector(ctx, &LINE_Y);
+    (with_intercept(chip, ctx, &x), y)
+}
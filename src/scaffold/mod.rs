@@ -47,26 +47,48 @@ pub struct CircuitScaffold<T, Fn> {
     private_inputs: T,
 }
 
+/// Runs the command given by `cli` on the circuit `f` with inputs read from the input file.
+/// Returns the public instances of the circuit (empty for `keygen`).
 pub fn run<T: DeserializeOwned>(
     f: impl FnOnce(&mut BaseCircuitBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
     cli: Cli,
+) -> Vec<Fr> {
+    let private_inputs = read_inputs(&cli);
+    run_on_inputs(f, cli, private_inputs)
+}
+
+/// Same as [`run`], but once the public instances are known (after running the mock prover,
+/// proving or verifying) they are passed to `report` with the inputs, e.g. to print dequantized
+/// outputs at the precision given in the inputs.
+pub fn run_with_report<T: DeserializeOwned + Clone>(
+    f: impl FnOnce(&mut BaseCircuitBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+    cli: Cli,
+    report: impl FnOnce(&T, &[Fr]),
 ) {
+    let command = cli.command;
+    let private_inputs: T = read_inputs(&cli);
+    let instances = run_on_inputs(f, cli, private_inputs.clone());
+    if !matches!(command, SnarkCmd::Keygen) {
+        report(&private_inputs, &instances);
+    }
+}
+
+fn read_inputs<T: DeserializeOwned>(cli: &Cli) -> T {
     let name = &cli.name;
     let input_path = PathBuf::from("data")
         .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
-    let private_inputs: T = serde_json::from_reader(
+    serde_json::from_reader(
         File::open(&input_path)
             .unwrap_or_else(|e| panic!("Input file not found at {input_path:?}. {e:?}")),
     )
-    .expect("Input file should be a valid JSON file");
-    run_on_inputs(f, cli, private_inputs)
+    .expect("Input file should be a valid JSON file")
 }
 
 pub fn run_on_inputs<T: DeserializeOwned>(
     f: impl FnOnce(&mut BaseCircuitBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
     cli: Cli,
     private_inputs: T,
-) {
+) -> Vec<Fr> {
     let precircuit = CircuitScaffold { f, private_inputs };
 
     let name = cli.name;
@@ -82,7 +104,9 @@ pub fn run_on_inputs<T: DeserializeOwned>(
     match cli.command {
         SnarkCmd::Mock => {
             let circuit = precircuit.create_circuit(CircuitBuilderStage::Mock, None, &params);
-            MockProver::run(k, &circuit, circuit.instances()).unwrap().assert_satisfied();
+            let instances = circuit.instances();
+            MockProver::run(k, &circuit, instances.clone()).unwrap().assert_satisfied();
+            instances.into_iter().next().unwrap_or_default()
         }
         SnarkCmd::Keygen => {
             let pk_path = data_path.join(PathBuf::from(format!("{name}.pk")));
@@ -113,6 +137,7 @@ pub fn run_on_inputs<T: DeserializeOwned>(
                 .write(&mut writer, SerdeFormat::RawBytes)
                 .expect("writing vkey should not fail");
             println!("Verifying key written to: {vk_path:?}");
+            vec![]
         }
         SnarkCmd::Prove => {
             let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
@@ -129,10 +154,11 @@ pub fn run_on_inputs<T: DeserializeOwned>(
                 fs::remove_file(&snark_path).unwrap();
             }
             let start = Instant::now();
-            gen_snark_shplonk(&params, &pk, circuit, Some(&snark_path));
+            let snark = gen_snark_shplonk(&params, &pk, circuit, Some(&snark_path));
             let prover_time = start.elapsed();
             println!("Proving time: {:?}", prover_time);
             println!("Snark written to: {snark_path:?}");
+            snark.instances.into_iter().next().unwrap_or_default()
         }
         SnarkCmd::Verify => {
             let vk_path = data_path.join(PathBuf::from(format!("{name}.vk")));
@@ -159,6 +185,7 @@ pub fn run_on_inputs<T: DeserializeOwned>(
             let verification_time = start.elapsed();
             println!("Snark verified successfully in {:?}", verification_time);
             circuit.clear();
+            snark.instances.into_iter().next().unwrap_or_default()
         }
     }
 }